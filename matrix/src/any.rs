@@ -0,0 +1,150 @@
+//! One type for all built-in effects, to switch between them at runtime
+//!
+//! `AnyEffect` is a plain enum, so it can live in a static or an RTIC resource
+//! without needing a heap for `Box<dyn Effect>`, and it can be used in a
+//! `Playlist` or `Compositor` to mix different effects.
+
+use crate::{
+	diagnostics::{
+		AddressTest,
+		Checkerboard,
+		ColourBars,
+		ColumnSweep,
+		Gradient,
+		RowSweep,
+		WalkingPixel,
+	},
+	params::{Param, ParamError, ParamValue},
+	shader::{Plasma, ShaderEffect},
+	CloudEffect,
+	Effect,
+	RectEffect,
+};
+use core::time::Duration;
+use embedded_graphics::{pixelcolor::Rgb565, DrawTarget};
+
+// generates the enum, the conversions from every effect and the dispatch
+macro_rules! effects {
+	($($variant:ident($effect:ty) = $name:literal => $new:expr,)*) => {
+		/// Any of the built-in effects
+		///
+		/// As big as the biggest effect, a few KB for the ones with their own
+		/// framebuffer. That's deliberate: boxing them needs a heap, and an
+		/// `AnyEffect` is usually the one effect of the program anyway.
+		#[allow(clippy::large_enum_variant)]
+		pub enum AnyEffect {
+			$($variant($effect),)*
+		}
+
+		impl AnyEffect {
+			/// The names of all effects, in the order used by `by_index`
+			pub const NAMES: &'static [&'static str] = &[$($name),*];
+
+			/// A fresh instance of the effect at `index` in `NAMES`
+			pub fn by_index(index: usize) -> Option<Self> {
+				let mut i = 0;
+				$(
+					if i == index {
+						return Some(AnyEffect::$variant($new));
+					}
+					i += 1;
+				)*
+				let _ = i;
+				None
+			}
+
+			/// A fresh instance of the effect called `name`
+			pub fn by_name(name: &str) -> Option<Self> {
+				Self::by_index(Self::NAMES.iter().position(|n| *n == name)?)
+			}
+
+			/// The position of this effect in `NAMES`
+			pub fn index(&self) -> usize {
+				Self::NAMES.iter().position(|n| *n == self.name()).unwrap()
+			}
+
+			pub fn name(&self) -> &'static str {
+				match self {
+					$(AnyEffect::$variant(_) => $name,)*
+				}
+			}
+		}
+
+		$(
+			impl From<$effect> for AnyEffect {
+				fn from(effect: $effect) -> Self {
+					AnyEffect::$variant(effect)
+				}
+			}
+		)*
+
+		impl Effect for AnyEffect {
+			fn step(&mut self, delta: Duration) {
+				match self {
+					$(AnyEffect::$variant(effect) => effect.step(delta),)*
+				}
+			}
+
+			fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+				match self {
+					$(AnyEffect::$variant(effect) => effect.render(target),)*
+				}
+			}
+
+			fn params(&self) -> &'static [Param] {
+				match self {
+					$(AnyEffect::$variant(effect) => effect.params(),)*
+				}
+			}
+
+			fn get(&self, name: &str) -> Option<ParamValue> {
+				match self {
+					$(AnyEffect::$variant(effect) => effect.get(name),)*
+				}
+			}
+
+			fn set(&mut self, name: &str, value: ParamValue) -> Result<(), ParamError> {
+				match self {
+					$(AnyEffect::$variant(effect) => effect.set(name, value),)*
+				}
+			}
+		}
+	};
+}
+
+effects! {
+	Cloud(CloudEffect) = "cloud" => CloudEffect::new(),
+	Rect(RectEffect) = "rect" => RectEffect::new(),
+	Plasma(ShaderEffect<Plasma, 2>) = "plasma" => Plasma::effect(),
+	WalkingPixel(WalkingPixel) = "walking_pixel" => WalkingPixel::new(),
+	RowSweep(RowSweep) = "row_sweep" => RowSweep::new(),
+	ColumnSweep(ColumnSweep) = "column_sweep" => ColumnSweep::new(),
+	ColourBars(ColourBars) = "colour_bars" => ColourBars::new(),
+	Gradient(Gradient) = "gradient" => Gradient::new(),
+	AddressTest(AddressTest) = "address_test" => AddressTest::new(),
+	Checkerboard(Checkerboard) = "checkerboard" => Checkerboard::new(),
+}
+
+impl AnyEffect {
+	/// Replace this effect with a fresh instance of the next one in `NAMES`,
+	/// wrapping around at the end
+	pub fn next(&mut self) {
+		let index = (self.index() + 1) % Self::NAMES.len();
+		*self = Self::by_index(index).unwrap();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn every_name_builds_its_effect() {
+		for (index, name) in AnyEffect::NAMES.iter().enumerate() {
+			let effect = AnyEffect::by_name(name).unwrap();
+			assert_eq!(effect.index(), index);
+			assert_eq!(effect.name(), *name);
+		}
+		assert!(AnyEffect::by_index(AnyEffect::NAMES.len()).is_none());
+	}
+}
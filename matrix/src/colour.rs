@@ -1,6 +1,18 @@
-use embedded_graphics::pixelcolor::Rgb565;
+pub mod correction;
+pub mod oklab;
+pub mod palette;
 
-#[derive(Copy, Clone, Debug)]
+use crate::math8::{lerp8by8, qsub8, scale8, scale8_video, sqrt16};
+use core::sync::atomic::{AtomicU32, Ordering};
+use embedded_graphics::pixelcolor::{
+	raw::{RawData, RawU24},
+	PixelColor,
+	Rgb565,
+	Rgb888,
+	RgbColor,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct HSV {
 	hue:        u8,
 	saturation: u8,
@@ -15,11 +27,104 @@ impl HSV {
 			value,
 		}
 	}
+
+	pub fn hue(&self) -> u8 {
+		self.hue
+	}
+
+	pub fn saturation(&self) -> u8 {
+		self.saturation
+	}
+
+	pub fn value(&self) -> u8 {
+		self.value
+	}
+
+	pub fn set_hue(&mut self, hue: u8) {
+		self.hue = hue;
+	}
+
+	pub fn set_saturation(&mut self, saturation: u8) {
+		self.saturation = saturation;
+	}
+
+	pub fn set_value(&mut self, value: u8) {
+		self.value = value;
+	}
+
+	/// Rotate the hue around the colour wheel
+	///
+	/// The hue wraps around, so `256 - n` rotates backwards by `n`.
+	pub fn rotate_hue(self, amount: u8) -> Self {
+		HSV::new(self.hue.wrapping_add(amount), self.saturation, self.value)
+	}
+
+	/// Scale the saturation by `scale / 256`, towards white
+	pub fn scale_saturation(self, scale: u8) -> Self {
+		HSV::new(self.hue, scale8(self.saturation, scale), self.value)
+	}
+
+	/// Scale the value by `scale / 256`, towards black
+	pub fn scale_value(self, scale: u8) -> Self {
+		HSV::new(self.hue, self.saturation, scale8(self.value, scale))
+	}
+
+	/// Interpolate towards `other`, `amount` 0 is `self` and 255 is (almost) `other`
+	///
+	/// The hue takes the shortest way around the colour wheel, so blending
+	/// red into purple doesn't pass through green and blue.
+	pub fn lerp(self, other: HSV, amount: u8) -> Self {
+		let delta = other.hue.wrapping_sub(self.hue) as i8 as i16;
+		let step = delta * amount as i16 / 255;
+		HSV::new(
+			self.hue.wrapping_add(step as u8),
+			lerp8by8(self.saturation, other.saturation, amount),
+			lerp8by8(self.value, other.value, amount),
+		)
+	}
+}
+
+/// Stored as `0xHHSSVV`
+impl PixelColor for HSV {
+	type Raw = RawU24;
+}
+
+impl From<RawU24> for HSV {
+	fn from(raw: RawU24) -> Self {
+		let raw = raw.into_inner();
+		HSV::new((raw >> 16) as u8, (raw >> 8) as u8, raw as u8)
+	}
+}
+
+impl From<HSV> for RawU24 {
+	fn from(hsv: HSV) -> Self {
+		RawU24::new((hsv.hue as u32) << 16 | (hsv.saturation as u32) << 8 | hsv.value as u32)
+	}
+}
+
+impl From<HSV> for Rgb888 {
+	fn from(hsv: HSV) -> Self {
+		hsv2rgb_rainbow(hsv, &HsvConfig::current())
+	}
 }
 
-impl Into<Rgb565> for HSV {
-	fn into(self) -> Rgb565 {
-		hsv2rgb_rainbow(self)
+impl From<HSV> for Rgb565 {
+	fn from(hsv: HSV) -> Self {
+		Rgb888::from(hsv).into()
+	}
+}
+
+/// Uses `rgb2hsv_approximate`, so this isn't exact
+impl From<Rgb888> for HSV {
+	fn from(rgb: Rgb888) -> Self {
+		rgb2hsv_approximate(rgb)
+	}
+}
+
+/// Uses `rgb2hsv_approximate`, so this isn't exact
+impl From<Rgb565> for HSV {
+	fn from(rgb: Rgb565) -> Self {
+		rgb2hsv_approximate(rgb.into())
 	}
 }
 
@@ -29,41 +134,87 @@ impl Default for HSV {
 	}
 }
 
-// from fastled
-fn scale8(i: u8, scale: u8) -> u8 {
-	(((i as u16) * (1 + scale as u16)) >> 8) as u8
+/// Yellow has a higher inherent brightness than
+/// any other color; 'pure' yellow is perceived to
+/// be 93% as bright as white.  In order to make
+/// yellow appear the correct relative brightness,
+/// it has to be rendered brighter than all other
+/// colors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum YellowBoost {
+	/// A moderate boost, the default (FastLED's Y1)
+	Moderate,
+	/// A strong boost (FastLED's Y2)
+	Strong,
+}
+
+/// Tuning for `hsv2rgb_rainbow`, which depends GREATLY on your particular LEDs
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HsvConfig {
+	pub yellow:      YellowBoost,
+	/// Whether to divide all greens by two
+	pub green_half:  bool,
+	/// What to scale green down by, 0 to leave it alone
+	pub green_scale: u8,
 }
 
-// from fastled
-fn scale8_video(i: u8, scale: u8) -> u8 {
-	(((i as usize * scale as usize) >> 8) + if i > 0 && scale > 0 { 1 } else { 0 }) as u8
+impl Default for HsvConfig {
+	fn default() -> Self {
+		HsvConfig {
+			yellow:      YellowBoost::Moderate,
+			green_half:  false,
+			green_scale: 0,
+		}
+	}
+}
+
+// Packed `HsvConfig`, used when converting `HSV` with `into`
+static CURRENT_CONFIG: AtomicU32 = AtomicU32::new(0);
+
+impl HsvConfig {
+	/// The config used when converting `HSV` with `into`
+	pub fn current() -> Self {
+		HsvConfig::unpack(CURRENT_CONFIG.load(Ordering::Relaxed))
+	}
+
+	/// Use this config for all conversions of `HSV` with `into`, from now on
+	pub fn install(self) {
+		CURRENT_CONFIG.store(self.pack(), Ordering::Relaxed);
+	}
+
+	fn pack(self) -> u32 {
+		let yellow = match self.yellow {
+			YellowBoost::Moderate => 0,
+			YellowBoost::Strong => 1,
+		};
+		yellow | (self.green_half as u32) << 1 | (self.green_scale as u32) << 8
+	}
+
+	fn unpack(packed: u32) -> Self {
+		HsvConfig {
+			yellow:      if packed & 1 == 0 {
+				YellowBoost::Moderate
+			} else {
+				YellowBoost::Strong
+			},
+			green_half:  packed & 2 != 0,
+			green_scale: (packed >> 8) as u8,
+		}
+	}
 }
 
-// from fastled
-fn hsv2rgb_rainbow(hsv: HSV) -> Rgb565 {
+/// Convert to RGB with even perceived brightness across the rainbow, from FastLED
+///
+/// Red, orange, yellow, green, aqua, blue, purple and pink each take up
+/// an eighth of the hue range.
+pub fn hsv2rgb_rainbow(hsv: HSV, config: &HsvConfig) -> Rgb888 {
 	const K255: u8 = 255;
 	const K171: u8 = 171;
 	const K170: u8 = 170;
 	const K85: u8 = 85;
 
-	// Yellow has a higher inherent brightness than
-	// any other color; 'pure' yellow is perceived to
-	// be 93% as bright as white.  In order to make
-	// yellow appear the correct relative brightness,
-	// it has to be rendered brighter than all other
-	// colors.
-	// Level Y1 is a moderate boost, the default.
-	// Level Y2 is a strong boost.
-	const Y1: bool = true;
-	const Y2: bool = false;
-
-	// G2: Whether to divide all greens by two.
-	// Depends GREATLY on your particular LEDs
-	const G2: bool = false;
-
-	// GSCALE: what to scale green down by.
-	// Depends GREATLY on your particular LEDs
-	const GSCALE: u8 = 0;
+	let y1 = config.yellow == YellowBoost::Moderate;
+	let y2 = config.yellow == YellowBoost::Strong;
 
 	let hue: u8 = hsv.hue;
 	let sat: u8 = hsv.saturation;
@@ -97,12 +248,12 @@ fn hsv2rgb_rainbow(hsv: HSV) -> Rgb565 {
 			} else {
 				// 001
 				//case 1: // O -> Y
-				if Y1 {
+				if y1 {
 					r = K171;
 					g = K85 + third;
 					b = 0;
 				}
-				if Y2 {
+				if y2 {
 					r = K170 + third;
 					//uint8_t twothirds = (third << 1);
 					let twothirds = scale8(offset8, ((256 * 2) / 3) as u8); // max=170
@@ -116,14 +267,14 @@ fn hsv2rgb_rainbow(hsv: HSV) -> Rgb565 {
 			if hue & 0x20 == 0 {
 				// 010
 				//case 2: // Y -> G
-				if Y1 {
+				if y1 {
 					//uint8_t twothirds = (third << 1);
 					let twothirds = scale8(offset8, ((256 * 2) / 3) as u8); // max=170
 					r = K171 - twothirds;
 					g = K170 + third;
 					b = 0;
 				}
-				if Y2 {
+				if y2 {
 					r = K255 - offset8;
 					g = K255;
 					b = 0;
@@ -178,11 +329,11 @@ fn hsv2rgb_rainbow(hsv: HSV) -> Rgb565 {
 
 	// This is one of the good places to scale the green down,
 	// although the client can scale green down as well.
-	if G2 {
-		g = g >> 1;
+	if config.green_half {
+		g >>= 1;
 	}
-	if GSCALE > 0 {
-		g = scale8_video(g, GSCALE);
+	if config.green_scale > 0 {
+		g = scale8_video(g, config.green_scale);
 	}
 
 	// Scale down colors if we're desaturated at all
@@ -234,5 +385,324 @@ fn hsv2rgb_rainbow(hsv: HSV) -> Rgb565 {
 		}
 	}
 
-	Rgb565::new(r, g, b)
+	Rgb888::new(r, g, b)
+}
+
+/// Convert to RGB with the hue mapped like a spectrum, from FastLED
+///
+/// Red, green and blue each take up a third of the hue range, so there is
+/// much less yellow and much more green than with `hsv2rgb_rainbow`.
+pub fn hsv2rgb_spectrum(hsv: HSV) -> Rgb888 {
+	hsv2rgb_raw(HSV::new(scale8(hsv.hue, 191), hsv.saturation, hsv.value))
+}
+
+/// Convert to RGB with a hue range of 0-191, from FastLED
+///
+/// The fastest conversion, but hues above 191 are invalid.
+pub fn hsv2rgb_raw(hsv: HSV) -> Rgb888 {
+	const HSV_SECTION_3: u8 = 0x40;
+
+	// Apply dimming curves, to make the output more visually linear
+	let value = scale8(hsv.value, hsv.value);
+	let saturation = hsv.saturation;
+
+	// The brightness floor is minimum number that all of
+	// R, G, and B will be set to.
+	let invsat = scale8(255 - saturation, 255 - saturation);
+	let brightness_floor = ((value as u16 * invsat as u16) / 256) as u8;
+
+	// The color amplitude is the maximum amount of R, G, and B
+	// that will be added on top of the brightness_floor to
+	// create the specific hue desired.
+	let color_amplitude = value - brightness_floor;
+
+	// Figure out which section of the hue wheel we're in,
+	// and how far offset we are withing that section
+	let section = hsv.hue / HSV_SECTION_3; // 0..2
+	let offset = hsv.hue % HSV_SECTION_3; // 0..63
+
+	let rampup = offset; // 0..63
+	let rampdown = (HSV_SECTION_3 - 1) - offset; // 63..0
+
+	// compute color-amplitude-scaled-down versions of rampup and rampdown
+	let rampup_amp_adj = ((rampup as u16 * color_amplitude as u16) / (256 / 4)) as u8;
+	let rampdown_amp_adj = ((rampdown as u16 * color_amplitude as u16) / (256 / 4)) as u8;
+
+	// add brightness_floor offset to everything
+	let rampup_adj_with_floor = rampup_amp_adj + brightness_floor;
+	let rampdown_adj_with_floor = rampdown_amp_adj + brightness_floor;
+
+	match section {
+		// section 0: 0x00..0x3F
+		0 => Rgb888::new(
+			rampdown_adj_with_floor,
+			rampup_adj_with_floor,
+			brightness_floor,
+		),
+		// section 1: 0x40..0x7F
+		1 => Rgb888::new(
+			brightness_floor,
+			rampdown_adj_with_floor,
+			rampup_adj_with_floor,
+		),
+		// section 2: 0x80..0xBF
+		_ => Rgb888::new(
+			rampup_adj_with_floor,
+			brightness_floor,
+			rampdown_adj_with_floor,
+		),
+	}
+}
+
+/// Convert RGB back to HSV, from FastLED
+///
+/// This is an approximation, which is close to the inverse of
+/// `hsv2rgb_rainbow` with the default config, but not exact.
+/// Converting a colour there and back can shift it a bit.
+pub fn rgb2hsv_approximate(rgb: Rgb888) -> HSV {
+	const HUE_RED: u8 = 0;
+	const HUE_ORANGE: u8 = 32;
+	const HUE_YELLOW: u8 = 64;
+	const HUE_GREEN: u8 = 96;
+	const HUE_AQUA: u8 = 128;
+	const HUE_BLUE: u8 = 160;
+	const HUE_PURPLE: u8 = 192;
+	const HUE_PINK: u8 = 224;
+
+	// (n * 256) / d
+	const FRAC_48_128: u8 = 96;
+	const FRAC_32_85: u8 = 96;
+	const FRAC_8_42: u8 = 48;
+	const FRAC_24_128: u8 = 48;
+
+	let mut r = rgb.r();
+	let mut g = rgb.g();
+	let mut b = rgb.b();
+
+	// find desaturation
+	let desat = r.min(g).min(b);
+
+	// remove saturation from all channels
+	r -= desat;
+	g -= desat;
+	b -= desat;
+
+	// saturation is opposite of desaturation
+	let mut s = 255 - desat;
+	if s != 255 {
+		// undo 'dimming' of saturation
+		s = 255 - sqrt16((255 - s) as u16 * 256);
+	}
+
+	// at least one channel is now zero
+	// if all three channels are zero, we had a
+	// shade of gray.
+	if r as u16 + g as u16 + b as u16 == 0 {
+		// we pick hue zero for no special reason
+		return HSV::new(0, 0, 255 - s);
+	}
+
+	// scale all channels up to compensate for desaturation
+	if s < 255 {
+		if s == 0 {
+			s = 1;
+		}
+		let scaleup = 65535 / s as u32;
+		r = (r as u32 * scaleup / 256) as u8;
+		g = (g as u32 * scaleup / 256) as u8;
+		b = (b as u32 * scaleup / 256) as u8;
+	}
+
+	let mut total = r as u16 + g as u16 + b as u16;
+
+	// scale all channels up to compensate for low values
+	if total < 255 {
+		if total == 0 {
+			total = 1;
+		}
+		let scaleup = 65535 / total as u32;
+		r = (r as u32 * scaleup / 256) as u8;
+		g = (g as u32 * scaleup / 256) as u8;
+		b = (b as u32 * scaleup / 256) as u8;
+	}
+
+	let v = if total > 255 {
+		255
+	} else {
+		let v = desat.saturating_add(total as u8);
+		// undo 'dimming' of brightness
+		if v != 255 {
+			sqrt16(v as u16 * 256)
+		} else {
+			v
+		}
+	};
+
+	// since this wasn't a pure shade of gray,
+	// the interesting question is what hue is it
+
+	// start with which channel is highest
+	// (ties don't matter)
+	let highest = r.max(g).max(b);
+
+	let h = if highest == r {
+		// Red is highest.
+		// Hue could be Purple/Pink-Red,Red-Orange,Orange-Yellow
+		if g == 0 {
+			// if green is zero, we're in Purple/Pink-Red
+			(HUE_PURPLE / 2 + HUE_PINK / 2).wrapping_add(scale8(qsub8(r, 128), FRAC_48_128))
+		} else if r - g > g {
+			// if R-G > G then we're in Red-Orange
+			HUE_RED.wrapping_add(scale8(g, FRAC_32_85))
+		} else {
+			// R-G < G, we're in Orange-Yellow
+			let adjust = ((g as i16 - 85) + (171 - r as i16)) as u8;
+			HUE_ORANGE.wrapping_add(scale8(qsub8(adjust, 4), FRAC_32_85))
+		}
+	} else if highest == g {
+		// Green is highest
+		// Hue could be Yellow-Green, Green-Aqua
+		if b == 0 {
+			// if Blue is zero, we're in Yellow-Green
+			//   G = 171..255
+			//   R = 171..  0
+			let radj = scale8(qsub8(171, r), 47);
+			let gadj = scale8(qsub8(g, 171), 96);
+			HUE_YELLOW + (radj + gadj) / 2
+		} else if g - b > b {
+			// if Blue is nonzero we're in Green-Aqua
+			HUE_GREEN.wrapping_add(scale8(b, FRAC_32_85))
+		} else {
+			HUE_AQUA.wrapping_add(scale8(qsub8(b, 85), FRAC_8_42))
+		}
+	} else {
+		// Blue is highest
+		// Hue could be Aqua/Blue-Blue, Blue-Purple, Purple-Pink
+		if r == 0 {
+			// if red is zero, we're in Aqua/Blue-Blue
+			(HUE_AQUA + (HUE_BLUE - HUE_AQUA) / 4).wrapping_add(scale8(qsub8(b, 128), FRAC_24_128))
+		} else if b - r > r {
+			// B-R > R, we're in Blue-Purple
+			HUE_BLUE.wrapping_add(scale8(r, FRAC_32_85))
+		} else {
+			// B-R < R, we're in Purple-Pink
+			HUE_PURPLE.wrapping_add(scale8(qsub8(r, 85), FRAC_32_85))
+		}
+	};
+
+	HSV::new(h.wrapping_add(1), s, v)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Distance between two hues on the colour wheel
+	fn hue_distance(a: u8, b: u8) -> u8 {
+		a.wrapping_sub(b).min(b.wrapping_sub(a))
+	}
+
+	#[test]
+	fn hsv_rgb_round_trip() {
+		for hue in 0..=255 {
+			for &saturation in &[255, 192] {
+				let hsv = HSV::new(hue, saturation, 255);
+				let back = rgb2hsv_approximate(Rgb888::from(hsv));
+				assert!(
+					hue_distance(back.hue(), hue) <= 1,
+					"{:?} -> {:?}",
+					hsv,
+					back
+				);
+				assert!(
+					back.saturation().abs_diff(saturation) <= 2,
+					"{:?} -> {:?}",
+					hsv,
+					back
+				);
+				assert_eq!(back.value(), 255, "{:?} -> {:?}", hsv, back);
+			}
+		}
+	}
+
+	#[test]
+	fn dim_colours_keep_their_hue() {
+		// the rainbow dims and desaturates along a curve the approximation
+		// doesn't undo, so only the hue comes back close
+		for hue in 0..=255 {
+			for &(saturation, value) in &[(255, 128), (255, 64), (128, 200)] {
+				let hsv = HSV::new(hue, saturation, value);
+				let back = rgb2hsv_approximate(Rgb888::from(hsv));
+				assert!(
+					hue_distance(back.hue(), hue) <= 6,
+					"{:?} -> {:?}",
+					hsv,
+					back
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn black_and_white() {
+		assert_eq!(Rgb888::from(HSV::new(123, 255, 0)), Rgb888::BLACK);
+		assert_eq!(rgb2hsv_approximate(Rgb888::BLACK).value(), 0);
+		assert_eq!(rgb2hsv_approximate(Rgb888::WHITE).saturation(), 0);
+	}
+
+	#[test]
+	fn hsv_config_packing() {
+		for &yellow in &[YellowBoost::Moderate, YellowBoost::Strong] {
+			for &green_half in &[false, true] {
+				for &green_scale in &[0, 1, 128, 255] {
+					let config = HsvConfig {
+						yellow,
+						green_half,
+						green_scale,
+					};
+					assert_eq!(HsvConfig::unpack(config.pack()), config);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn default_config_is_current() {
+		// other tests convert with `into` in parallel, so only the default is
+		// installed here
+		assert_eq!(HsvConfig::current(), HsvConfig::default());
+		HsvConfig::default().install();
+		assert_eq!(HsvConfig::current(), HsvConfig::default());
+	}
+
+	#[test]
+	fn raw_matches_fastled() {
+		let cases = [
+			((0, 255, 255), (251, 0, 0)),
+			((32, 255, 255), (123, 127, 0)),
+			((64, 255, 255), (0, 251, 0)),
+			((128, 255, 255), (0, 0, 251)),
+			((100, 128, 200), (38, 88, 104)),
+			((160, 255, 128), (32, 0, 31)),
+			((0, 0, 255), (254, 254, 254)),
+			((0, 0, 0), (0, 0, 0)),
+		];
+		for &((h, s, v), (r, g, b)) in &cases {
+			assert_eq!(hsv2rgb_raw(HSV::new(h, s, v)), Rgb888::new(r, g, b));
+		}
+	}
+
+	#[test]
+	fn spectrum_matches_fastled() {
+		let cases = [
+			((0, 255, 255), (251, 0, 0)),
+			((85, 255, 255), (0, 251, 0)),
+			((170, 255, 255), (0, 0, 251)),
+			((255, 255, 255), (251, 0, 0)),
+			((128, 192, 160), (5, 51, 52)),
+		];
+		for &((h, s, v), (r, g, b)) in &cases {
+			assert_eq!(hsv2rgb_spectrum(HSV::new(h, s, v)), Rgb888::new(r, g, b));
+		}
+	}
 }
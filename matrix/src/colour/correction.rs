@@ -0,0 +1,155 @@
+//! Colour correction and colour temperature, like FastLED's `setCorrection`
+//! and `setTemperature`
+//!
+//! The correction makes up for the LEDs themselves, which usually have a
+//! much stronger green and blue than red. The temperature tints everything
+//! to match the light in the room. Both are applied by `Hub75` just before
+//! gamma correction, so effects don't need to know about them.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+
+const fn hex(code: u32) -> Rgb888 {
+	Rgb888::new((code >> 16) as u8, (code >> 8) as u8, code as u8)
+}
+
+// from fastled, color corrections
+
+/// Typical values for SMD5050 LEDs
+pub const TYPICAL_SMD5050: Rgb888 = hex(0xFFB0F0);
+/// Typical values for generic LED strips
+pub const TYPICAL_LED_STRIP: Rgb888 = hex(0xFFB0F0);
+/// Typical values for 8mm "pixels on a string"
+pub const TYPICAL_8MM_PIXEL: Rgb888 = hex(0xFFE08C);
+/// Typical values for "pixels on a string"
+pub const TYPICAL_PIXEL_STRING: Rgb888 = hex(0xFFE08C);
+/// Uncorrected colour
+pub const UNCORRECTED_COLOR: Rgb888 = hex(0xFFFFFF);
+
+// from fastled, black body radiators
+
+/// 1900 Kelvin
+pub const CANDLE: Rgb888 = hex(0xFF9329);
+/// 2600 Kelvin
+pub const TUNGSTEN_40W: Rgb888 = hex(0xFFC58F);
+/// 2850 Kelvin
+pub const TUNGSTEN_100W: Rgb888 = hex(0xFFD6AA);
+/// 3200 Kelvin
+pub const HALOGEN: Rgb888 = hex(0xFFF1E0);
+/// 5200 Kelvin
+pub const CARBON_ARC: Rgb888 = hex(0xFFFAF4);
+/// 5400 Kelvin
+pub const HIGH_NOON_SUN: Rgb888 = hex(0xFFFFFB);
+/// 6000 Kelvin
+pub const DIRECT_SUNLIGHT: Rgb888 = hex(0xFFFFFF);
+/// 7000 Kelvin
+pub const OVERCAST_SKY: Rgb888 = hex(0xC9E2FF);
+/// 20000 Kelvin
+pub const CLEAR_BLUE_SKY: Rgb888 = hex(0x409CFF);
+
+// from fastled, gaseous light sources
+
+pub const WARM_FLUORESCENT: Rgb888 = hex(0xFFF4E5);
+pub const STANDARD_FLUORESCENT: Rgb888 = hex(0xF4FFFA);
+pub const COOL_WHITE_FLUORESCENT: Rgb888 = hex(0xD4EBFF);
+pub const FULL_SPECTRUM_FLUORESCENT: Rgb888 = hex(0xFFF4F2);
+pub const GROW_LIGHT_FLUORESCENT: Rgb888 = hex(0xFFEFF7);
+pub const BLACK_LIGHT_FLUORESCENT: Rgb888 = hex(0xA700FF);
+pub const MERCURY_VAPOR: Rgb888 = hex(0xD8F7FF);
+pub const SODIUM_VAPOR: Rgb888 = hex(0xFFD1B2);
+pub const METAL_HALIDE: Rgb888 = hex(0xF2FCFF);
+pub const HIGH_PRESSURE_SODIUM: Rgb888 = hex(0xFFB74C);
+
+/// Uncorrected temperature
+pub const UNCORRECTED_TEMPERATURE: Rgb888 = hex(0xFFFFFF);
+
+/// Colour correction and temperature, scaling every channel of every pixel
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Correction {
+	pub correction:  Rgb888,
+	pub temperature: Rgb888,
+}
+
+impl Default for Correction {
+	fn default() -> Self {
+		Correction {
+			correction:  UNCORRECTED_COLOR,
+			temperature: UNCORRECTED_TEMPERATURE,
+		}
+	}
+}
+
+fn pack(rgb: Rgb888) -> u32 {
+	((rgb.r() as u32) << 16) | ((rgb.g() as u32) << 8) | rgb.b() as u32
+}
+
+// The packed adjustment of the installed `Correction`
+//
+// A single atomic, so a pixel is always scaled by either the old or the new
+// correction, never a mix of both.
+static CURRENT_ADJUSTMENT: AtomicU32 = AtomicU32::new(0xFFFFFF);
+
+impl Correction {
+	pub fn new(correction: Rgb888, temperature: Rgb888) -> Self {
+		Correction {
+			correction,
+			temperature,
+		}
+	}
+
+	/// The combined scale for every channel, from FastLED's `computeAdjustment`
+	pub fn adjustment(&self) -> Rgb888 {
+		let channel = |cc: u8, ct: u8| {
+			if cc == 0 || ct == 0 {
+				0
+			} else {
+				((cc as u32 + 1) * (ct as u32 + 1) * 255 / 0x10000) as u8
+			}
+		};
+		Rgb888::new(
+			channel(self.correction.r(), self.temperature.r()),
+			channel(self.correction.g(), self.temperature.g()),
+			channel(self.correction.b(), self.temperature.b()),
+		)
+	}
+
+	/// Use this correction for everything drawn to `Hub75`, from now on
+	///
+	/// Pixels already drawn keep their old colours until they're drawn again.
+	pub fn install(self) {
+		CURRENT_ADJUSTMENT.store(pack(self.adjustment()), Ordering::Relaxed);
+	}
+}
+
+/// The adjustment of the installed correction, as used by `Hub75`
+pub fn current_adjustment() -> Rgb888 {
+	hex(CURRENT_ADJUSTMENT.load(Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn uncorrected_is_full_scale() {
+		assert_eq!(Correction::default().adjustment(), Rgb888::WHITE);
+	}
+
+	#[test]
+	fn adjustment_matches_fastled() {
+		let smd5050 = Correction::new(TYPICAL_SMD5050, UNCORRECTED_TEMPERATURE);
+		assert_eq!(smd5050.adjustment(), Rgb888::new(255, 176, 240));
+		let candle = Correction::new(TYPICAL_SMD5050, CANDLE);
+		assert_eq!(candle.adjustment(), Rgb888::new(255, 101, 39));
+		let black_light = Correction::new(UNCORRECTED_COLOR, BLACK_LIGHT_FLUORESCENT);
+		assert_eq!(black_light.adjustment(), Rgb888::new(167, 0, 255));
+	}
+
+	#[test]
+	fn install_sets_the_adjustment() {
+		Correction::new(TYPICAL_SMD5050, CANDLE).install();
+		assert_eq!(current_adjustment(), Rgb888::new(255, 101, 39));
+		Correction::default().install();
+		assert_eq!(current_adjustment(), Rgb888::WHITE);
+	}
+}
@@ -0,0 +1,260 @@
+//! The OKLab and OKLCH perceptual colour spaces, by Björn Ottosson
+//!
+//! Interpolating in OKLab keeps the perceived brightness even and avoids the
+//! muddy greys you get halfway between complementary colours in RGB.
+//! OKLCH is the same space in polar coordinates, so hues can be blended
+//! around the colour wheel.
+
+use core::f32::consts::PI;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use libm::{atan2f, cbrtf, cosf, powf, sinf, sqrtf};
+
+/// RGB without the sRGB transfer curve, every channel from 0.0 to 1.0
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LinearRgb {
+	pub r: f32,
+	pub g: f32,
+	pub b: f32,
+}
+
+impl LinearRgb {
+	pub const fn new(r: f32, g: f32, b: f32) -> Self {
+		LinearRgb { r, g, b }
+	}
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+	let c = c as f32 / 255.0;
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		powf((c + 0.055) / 1.055, 2.4)
+	}
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+	let c = c.clamp(0.0, 1.0);
+	let c = if c <= 0.003_130_8 {
+		c * 12.92
+	} else {
+		1.055 * powf(c, 1.0 / 2.4) - 0.055
+	};
+	(c * 255.0 + 0.5) as u8
+}
+
+impl From<Rgb888> for LinearRgb {
+	fn from(rgb: Rgb888) -> Self {
+		LinearRgb::new(
+			srgb_to_linear(rgb.r()),
+			srgb_to_linear(rgb.g()),
+			srgb_to_linear(rgb.b()),
+		)
+	}
+}
+
+impl From<LinearRgb> for Rgb888 {
+	/// Colours outside of the sRGB gamut are clipped
+	fn from(rgb: LinearRgb) -> Self {
+		Rgb888::new(
+			linear_to_srgb(rgb.r),
+			linear_to_srgb(rgb.g),
+			linear_to_srgb(rgb.b),
+		)
+	}
+}
+
+/// A colour in OKLab, `l` is the lightness from 0.0 to 1.0,
+/// `a` (green to red) and `b` (blue to yellow) are roughly within ±0.4
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Oklab {
+	pub l: f32,
+	pub a: f32,
+	pub b: f32,
+}
+
+impl Oklab {
+	pub const fn new(l: f32, a: f32, b: f32) -> Self {
+		Oklab { l, a, b }
+	}
+
+	/// Interpolate towards `other`, `t` 0.0 is `self` and 1.0 is `other`
+	pub fn lerp(self, other: Oklab, t: f32) -> Self {
+		Oklab::new(
+			self.l + (other.l - self.l) * t,
+			self.a + (other.a - self.a) * t,
+			self.b + (other.b - self.b) * t,
+		)
+	}
+}
+
+impl From<LinearRgb> for Oklab {
+	fn from(rgb: LinearRgb) -> Self {
+		let l = 0.412_221_46 * rgb.r + 0.536_332_55 * rgb.g + 0.051_445_995 * rgb.b;
+		let m = 0.211_903_5 * rgb.r + 0.680_699_5 * rgb.g + 0.107_396_96 * rgb.b;
+		let s = 0.088_302_46 * rgb.r + 0.281_718_85 * rgb.g + 0.629_978_7 * rgb.b;
+
+		let l = cbrtf(l);
+		let m = cbrtf(m);
+		let s = cbrtf(s);
+
+		Oklab::new(
+			0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+			1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+			0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+		)
+	}
+}
+
+impl From<Oklab> for LinearRgb {
+	fn from(lab: Oklab) -> Self {
+		let l = lab.l + 0.396_337_78 * lab.a + 0.215_803_76 * lab.b;
+		let m = lab.l - 0.105_561_346 * lab.a - 0.063_854_17 * lab.b;
+		let s = lab.l - 0.089_484_18 * lab.a - 1.291_485_5 * lab.b;
+
+		let l = l * l * l;
+		let m = m * m * m;
+		let s = s * s * s;
+
+		LinearRgb::new(
+			4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+			-1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+			-0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+		)
+	}
+}
+
+impl From<Rgb888> for Oklab {
+	fn from(rgb: Rgb888) -> Self {
+		LinearRgb::from(rgb).into()
+	}
+}
+
+impl From<Oklab> for Rgb888 {
+	fn from(lab: Oklab) -> Self {
+		LinearRgb::from(lab).into()
+	}
+}
+
+/// A colour in OKLCH, `l` is the lightness from 0.0 to 1.0, `c` the chroma
+/// (0.0 is grey) and `h` the hue angle in radians
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Oklch {
+	pub l: f32,
+	pub c: f32,
+	pub h: f32,
+}
+
+impl Oklch {
+	pub const fn new(l: f32, c: f32, h: f32) -> Self {
+		Oklch { l, c, h }
+	}
+
+	/// Interpolate towards `other`, `t` 0.0 is `self` and 1.0 is `other`
+	///
+	/// The hue takes the shortest way around the colour wheel.
+	pub fn lerp(self, other: Oklch, t: f32) -> Self {
+		let mut delta = (other.h - self.h) % (2.0 * PI);
+		if delta > PI {
+			delta -= 2.0 * PI;
+		} else if delta < -PI {
+			delta += 2.0 * PI;
+		}
+		Oklch::new(
+			self.l + (other.l - self.l) * t,
+			self.c + (other.c - self.c) * t,
+			self.h + delta * t,
+		)
+	}
+}
+
+impl From<Oklab> for Oklch {
+	fn from(lab: Oklab) -> Self {
+		Oklch::new(
+			lab.l,
+			sqrtf(lab.a * lab.a + lab.b * lab.b),
+			atan2f(lab.b, lab.a),
+		)
+	}
+}
+
+impl From<Oklch> for Oklab {
+	fn from(lch: Oklch) -> Self {
+		Oklab::new(lch.l, lch.c * cosf(lch.h), lch.c * sinf(lch.h))
+	}
+}
+
+impl From<Rgb888> for Oklch {
+	fn from(rgb: Rgb888) -> Self {
+		Oklab::from(rgb).into()
+	}
+}
+
+impl From<Oklch> for Rgb888 {
+	fn from(lch: Oklch) -> Self {
+		Oklab::from(lch).into()
+	}
+}
+
+/// Blend two colours in OKLab, `amount` 0 is `a` and 255 is `b`
+pub fn blend(a: Rgb888, b: Rgb888, amount: u8) -> Rgb888 {
+	match amount {
+		0 => a,
+		255 => b,
+		_ => Oklab::from(a).lerp(b.into(), amount as f32 / 255.0).into(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(a: Oklab, b: Oklab) {
+		assert!(
+			(a.l - b.l).abs() < 1e-3 && (a.a - b.a).abs() < 1e-3 && (a.b - b.b).abs() < 1e-3,
+			"{:?} != {:?}",
+			a,
+			b
+		);
+	}
+
+	#[test]
+	fn srgb_round_trip() {
+		for &r in &[0, 1, 17, 64, 128, 200, 254, 255] {
+			for &g in &[0, 3, 50, 127, 255] {
+				for &b in &[0, 99, 180, 255] {
+					let rgb = Rgb888::new(r, g, b);
+					assert_eq!(Rgb888::from(Oklab::from(rgb)), rgb);
+					assert_eq!(Rgb888::from(Oklch::from(rgb)), rgb);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn white_and_black() {
+		assert_close(Oklab::from(Rgb888::WHITE), Oklab::new(1.0, 0.0, 0.0));
+		assert_eq!(Oklab::from(Rgb888::BLACK), Oklab::new(0.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn lerp_endpoints() {
+		let red = Oklab::from(Rgb888::RED);
+		let blue = Oklab::from(Rgb888::BLUE);
+		assert_eq!(red.lerp(blue, 0.0), red);
+		assert_close(red.lerp(blue, 1.0), blue);
+
+		let red = Oklch::from(Rgb888::RED);
+		let blue = Oklch::from(Rgb888::BLUE);
+		assert_eq!(red.lerp(blue, 0.0), red);
+		assert_close(red.lerp(blue, 1.0).into(), blue.into());
+	}
+
+	#[test]
+	fn blend_endpoints() {
+		let a = Rgb888::new(255, 128, 0);
+		let b = Rgb888::new(0, 64, 255);
+		assert_eq!(blend(a, b, 0), a);
+		assert_eq!(blend(a, b, 255), b);
+		assert_eq!(blend(a, a, 128), a);
+	}
+}
@@ -0,0 +1,231 @@
+//! Palettes map an 8 bit index to a colour, like FastLED's `CRGBPalette16`
+//! and gradient palettes.
+
+use super::oklab;
+use crate::math8::blend8;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+
+/// Something that maps an index (0-255) to a colour
+pub trait Palette {
+	fn lookup(&self, index: u8) -> Rgb888;
+}
+
+fn blend(a: Rgb888, b: Rgb888, amount: u8) -> Rgb888 {
+	Rgb888::new(
+		blend8(a.r(), b.r(), amount),
+		blend8(a.g(), b.g(), amount),
+		blend8(a.b(), b.b(), amount),
+	)
+}
+
+const fn hex(code: u32) -> Rgb888 {
+	Rgb888::new((code >> 16) as u8, (code >> 8) as u8, code as u8)
+}
+
+/// 16 evenly spaced colours, with the ones in between interpolated
+///
+/// The last entry blends back into the first one, so it can be used for
+/// indices that wrap around.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Palette16 {
+	entries: [Rgb888; 16],
+}
+
+impl Palette16 {
+	pub const fn new(entries: [Rgb888; 16]) -> Self {
+		Palette16 { entries }
+	}
+
+	/// Create a palette from `0xRRGGBB` colour codes
+	pub const fn from_hex(codes: [u32; 16]) -> Self {
+		let mut entries = [Rgb888::BLACK; 16];
+		let mut i = 0;
+		while i < 16 {
+			entries[i] = hex(codes[i]);
+			i += 1;
+		}
+		Palette16 { entries }
+	}
+
+	pub fn entries(&self) -> &[Rgb888; 16] {
+		&self.entries
+	}
+
+	pub fn entries_mut(&mut self) -> &mut [Rgb888; 16] {
+		&mut self.entries
+	}
+}
+
+impl Palette for Palette16 {
+	fn lookup(&self, index: u8) -> Rgb888 {
+		let hi4 = (index >> 4) as usize;
+		let lo4 = index & 0x0F;
+
+		let entry = self.entries[hi4];
+		if lo4 == 0 {
+			return entry;
+		}
+		let next = self.entries[(hi4 + 1) % 16];
+		blend(entry, next, lo4 << 4)
+	}
+}
+
+/// A colour at a position of a gradient
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GradientStop {
+	pub position: u8,
+	pub colour:   Rgb888,
+}
+
+impl GradientStop {
+	pub const fn new(position: u8, colour: Rgb888) -> Self {
+		GradientStop { position, colour }
+	}
+}
+
+/// A gradient between any number of stops
+///
+/// The stops have to be sorted by position. Indices before the first or after
+/// the last stop take the colour of that stop.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GradientPalette<'a> {
+	stops: &'a [GradientStop],
+}
+
+impl<'a> GradientPalette<'a> {
+	pub const fn new(stops: &'a [GradientStop]) -> Self {
+		GradientPalette { stops }
+	}
+
+	/// Sample the gradient into a `Palette16`, which is faster to look up
+	pub fn to_palette16(&self) -> Palette16 {
+		let mut entries = [Rgb888::BLACK; 16];
+		for (i, entry) in entries.iter_mut().enumerate() {
+			*entry = self.lookup((i * 255 / 15) as u8);
+		}
+		Palette16::new(entries)
+	}
+
+	/// Like `lookup`, but blends between the stops in OKLab
+	///
+	/// Much slower, meant for sampling the gradient once with `to_palette16_oklab`.
+	pub fn lookup_oklab(&self, index: u8) -> Rgb888 {
+		self.lookup_with(index, oklab::blend)
+	}
+
+	/// Like `to_palette16`, but with the gradient blended in OKLab
+	pub fn to_palette16_oklab(&self) -> Palette16 {
+		let mut entries = [Rgb888::BLACK; 16];
+		for (i, entry) in entries.iter_mut().enumerate() {
+			*entry = self.lookup_oklab((i * 255 / 15) as u8);
+		}
+		Palette16::new(entries)
+	}
+
+	fn lookup_with(&self, index: u8, blend: fn(Rgb888, Rgb888, u8) -> Rgb888) -> Rgb888 {
+		let mut previous = match self.stops.first() {
+			Some(stop) => stop,
+			None => return Rgb888::BLACK,
+		};
+		for stop in self.stops.iter() {
+			if stop.position >= index {
+				if stop.position == previous.position {
+					return stop.colour;
+				}
+				let span = (stop.position - previous.position) as u16;
+				let amount = ((index - previous.position) as u16 * 255 / span) as u8;
+				return blend(previous.colour, stop.colour, amount);
+			}
+			previous = stop;
+		}
+		previous.colour
+	}
+}
+
+impl Palette for GradientPalette<'_> {
+	fn lookup(&self, index: u8) -> Rgb888 {
+		self.lookup_with(index, blend)
+	}
+}
+
+/// Black through dark reds to orange and white
+pub const LAVA: Palette16 = Palette16::from_hex([
+	0x000000, 0x800000, 0x000000, 0x800000, 0x8B0000, 0x8B0000, 0x800000, 0x8B0000, 0x8B0000,
+	0x8B0000, 0xFF0000, 0xFFA500, 0xFFFFFF, 0xFFA500, 0xFF0000, 0x8B0000,
+]);
+
+/// Deep blues, teals and aquamarine
+pub const OCEAN: Palette16 = Palette16::from_hex([
+	0x191970, 0x00008B, 0x191970, 0x000080, 0x00008B, 0x0000CD, 0x2E8B57, 0x008080, 0x5F9EA0,
+	0x0000FF, 0x008B8B, 0x6495ED, 0x7FFFD4, 0x2E8B57, 0x00FFFF, 0x87CEFA,
+]);
+
+/// Dark and light greens
+pub const FOREST: Palette16 = Palette16::from_hex([
+	0x006400, 0x006400, 0x556B2F, 0x006400, 0x008000, 0x228B22, 0x6B8E23, 0x008000, 0x2E8B57,
+	0x66CDAA, 0x32CD32, 0x9ACD32, 0x90EE90, 0x7CFC00, 0x66CDAA, 0x228B22,
+]);
+
+/// Black through red and yellow to white, like glowing metal
+pub const HEAT: Palette16 = Palette16::from_hex([
+	0x000000, 0x330000, 0x660000, 0x990000, 0xCC0000, 0xFF0000, 0xFF3300, 0xFF6600, 0xFF9900,
+	0xFFCC00, 0xFFFF00, 0xFFFF33, 0xFFFF66, 0xFFFF99, 0xFFFFCC, 0xFFFFFF,
+]);
+
+/// A rainbow without the greens
+pub const PARTY: Palette16 = Palette16::from_hex([
+	0x5500AB, 0x84007C, 0xB5004B, 0xE5001B, 0xE81700, 0xB84700, 0xAB7700, 0xABAB00, 0xAB5500,
+	0xDD2200, 0xF2000E, 0xC2003E, 0x8F0071, 0x5F00A1, 0x2F00D0, 0x0007F9,
+]);
+
+/// All built-in palettes, in the order used by palette parameters
+pub const BUILTIN: [&Palette16; 5] = [&LAVA, &OCEAN, &FOREST, &HEAT, &PARTY];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn grey(level: u32) -> u32 {
+		(level << 16) | (level << 8) | level
+	}
+
+	#[test]
+	fn palette16_ends_and_wraparound() {
+		let mut codes = [0; 16];
+		for (i, code) in codes.iter_mut().enumerate() {
+			*code = grey(i as u32 * 16);
+		}
+		let palette = Palette16::from_hex(codes);
+		assert_eq!(palette.lookup(0), Rgb888::BLACK);
+		assert_eq!(palette.lookup(240), Rgb888::new(240, 240, 240));
+		// past the last entry, the palette blends back towards the first one
+		assert_eq!(palette.lookup(248), Rgb888::new(120, 120, 120));
+		assert!(palette.lookup(255).r() < 30);
+		assert_eq!(palette.lookup(8), Rgb888::new(8, 8, 8));
+	}
+
+	#[test]
+	fn gradient_ends() {
+		const STOPS: [GradientStop; 3] = [
+			GradientStop::new(16, Rgb888::RED),
+			GradientStop::new(128, Rgb888::GREEN),
+			GradientStop::new(240, Rgb888::BLUE),
+		];
+		let gradient = GradientPalette::new(&STOPS);
+		// before the first and after the last stop, the colour stays the same
+		assert_eq!(gradient.lookup(0), Rgb888::RED);
+		assert_eq!(gradient.lookup(16), Rgb888::RED);
+		assert_eq!(gradient.lookup(128), Rgb888::GREEN);
+		assert_eq!(gradient.lookup(240), Rgb888::BLUE);
+		assert_eq!(gradient.lookup(255), Rgb888::BLUE);
+
+		let palette = gradient.to_palette16();
+		assert_eq!(palette.entries()[0], Rgb888::RED);
+		assert_eq!(palette.entries()[15], Rgb888::BLUE);
+	}
+
+	#[test]
+	fn empty_gradient_is_black() {
+		assert_eq!(GradientPalette::new(&[]).lookup(100), Rgb888::BLACK);
+	}
+}
@@ -0,0 +1,257 @@
+use crate::{framebuffer::Framebuffer, Effect, HEIGHT, WIDTH};
+use core::{convert::Infallible, time::Duration};
+use embedded_graphics::{
+	drawable::Pixel,
+	geometry::Size,
+	pixelcolor::{Rgb565, RgbColor},
+	DrawTarget,
+};
+
+/// How a layer is combined with the layers below it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+	/// Cover the layers below
+	Normal,
+	/// Add the colours, black is transparent
+	Add,
+	/// Multiply the colours, darkening, white is transparent
+	Multiply,
+	/// Inverse of multiplying the inverted colours, brightening, black is transparent
+	Screen,
+	/// The brighter of both, per channel, black is transparent
+	Lighten,
+}
+
+impl BlendMode {
+	/// Blend one channel, both from 0 to `max`
+	fn channel(self, below: u8, above: u8, max: u8) -> u8 {
+		let (d, s, m) = (below as u16, above as u16, max as u16);
+		let blended = match self {
+			BlendMode::Normal => s,
+			BlendMode::Add => (d + s).min(m),
+			BlendMode::Multiply => d * s / m,
+			BlendMode::Screen => m - (m - d) * (m - s) / m,
+			BlendMode::Lighten => d.max(s),
+		};
+		blended as u8
+	}
+}
+
+/// A layer of a compositor, an effect and how to blend it
+pub struct Layer<E> {
+	pub effect:  E,
+	pub blend:   BlendMode,
+	/// 0 is invisible, 255 fully blended
+	pub opacity: u8,
+}
+
+impl<E> Layer<E> {
+	pub fn new(effect: E, blend: BlendMode, opacity: u8) -> Self {
+		Layer {
+			effect,
+			blend,
+			opacity,
+		}
+	}
+}
+
+/// Stacks a fixed list of effects on top of each other
+///
+/// The first layer is at the bottom, and is blended onto black. Like with
+/// `Playlist`, all layers share one effect type, so mixing different
+/// effects needs an enum implementing `Effect` over them.
+///
+/// Only the pixels a layer actually draws are blended, the rest of it is
+/// transparent. So an overlay like a clock can use `Normal` as long as it
+/// only draws its digits. Effects that clear the target cover the whole
+/// layer, use `Add`, `Screen` or `Lighten` to let their black show through.
+///
+/// Every layer is stepped and composited on `step`, `render` only draws the result.
+pub struct Compositor<E, const N: usize> {
+	layers:  [Layer<E>; N],
+	scratch: Framebuffer<Rgb565, WIDTH, HEIGHT>,
+	/// One bit for every pixel of `scratch` the current layer has drawn
+	mask:    [u64; HEIGHT],
+	frame:   Framebuffer<Rgb565, WIDTH, HEIGHT>,
+}
+
+impl<E: Effect, const N: usize> Compositor<E, N> {
+	pub fn new(layers: [Layer<E>; N]) -> Self {
+		Compositor {
+			layers,
+			scratch: Framebuffer::new(Rgb565::BLACK),
+			mask: [0; HEIGHT],
+			frame: Framebuffer::new(Rgb565::BLACK),
+		}
+	}
+
+	pub fn layers(&self) -> &[Layer<E>; N] {
+		&self.layers
+	}
+
+	pub fn layers_mut(&mut self) -> &mut [Layer<E>; N] {
+		&mut self.layers
+	}
+
+	/// The composited frame, as of the last `step`
+	pub fn frame(&self) -> &Framebuffer<Rgb565, WIDTH, HEIGHT> {
+		&self.frame
+	}
+
+	fn composite(&mut self, layer: usize) {
+		let Layer { blend, opacity, .. } = self.layers[layer];
+		let rows = self
+			.frame
+			.pixels_mut()
+			.iter_mut()
+			.zip(self.scratch.pixels());
+		for ((below, above), mask) in rows.zip(self.mask.iter()) {
+			for (x, (d, &s)) in below.iter_mut().zip(above.iter()).enumerate() {
+				if mask & (1 << x) == 0 {
+					continue;
+				}
+				let channel = |below: u8, above: u8, max: u8| {
+					let blended = blend.channel(below, above, max) as i16;
+					let below = below as i16;
+					(below + (blended - below) * opacity as i16 / 255) as u8
+				};
+				*d = Rgb565::new(
+					channel(d.r(), s.r(), Rgb565::MAX_R),
+					channel(d.g(), s.g(), Rgb565::MAX_G),
+					channel(d.b(), s.b(), Rgb565::MAX_B),
+				);
+			}
+		}
+	}
+}
+
+impl<E: Effect, const N: usize> Effect for Compositor<E, N> {
+	fn step(&mut self, delta: Duration) {
+		self.frame.fill(Rgb565::BLACK);
+		for layer in 0..N {
+			self.layers[layer].effect.step(delta);
+			if self.layers[layer].opacity == 0 {
+				continue;
+			}
+			self.mask = [0; HEIGHT];
+			let mut target = Masked {
+				frame: &mut self.scratch,
+				mask:  &mut self.mask,
+			};
+			self.layers[layer].effect.render(&mut target).unwrap();
+			self.composite(layer);
+		}
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		self.frame.render(target)
+	}
+}
+
+/// Draws into a framebuffer, remembering which pixels were drawn
+struct Masked<'a> {
+	frame: &'a mut Framebuffer<Rgb565, WIDTH, HEIGHT>,
+	// a row of the panel fits into one u64
+	mask:  &'a mut [u64; HEIGHT],
+}
+
+impl DrawTarget<Rgb565> for Masked<'_> {
+	type Error = Infallible;
+
+	fn draw_pixel(&mut self, item: Pixel<Rgb565>) -> Result<(), Self::Error> {
+		let Pixel(point, colour) = item;
+		if point.x >= 0 && point.y >= 0 && point.x < WIDTH as i32 && point.y < HEIGHT as i32 {
+			self.frame.pixels_mut()[point.y as usize][point.x as usize] = colour;
+			self.mask[point.y as usize] |= 1 << point.x;
+		}
+		Ok(())
+	}
+
+	fn size(&self) -> Size {
+		Size::new(WIDTH as u32, HEIGHT as u32)
+	}
+
+	fn clear(&mut self, colour: Rgb565) -> Result<(), Self::Error> {
+		self.frame.fill(colour);
+		*self.mask = [u64::MAX; HEIGHT];
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use embedded_graphics::prelude::Point;
+
+	/// Fills the screen with one colour
+	struct Solid(Rgb565);
+
+	impl Effect for Solid {
+		fn step(&mut self, _delta: Duration) {}
+
+		fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+			target.clear(self.0)
+		}
+	}
+
+	/// Draws a single pixel at the top left
+	struct Dot(Rgb565);
+
+	impl Effect for Dot {
+		fn step(&mut self, _delta: Duration) {}
+
+		fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+			target.draw_pixel(Pixel(Point::zero(), self.0))
+		}
+	}
+
+	enum Layers {
+		Solid(Solid),
+		Dot(Dot),
+	}
+
+	impl Effect for Layers {
+		fn step(&mut self, _delta: Duration) {}
+
+		fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+			match self {
+				Layers::Solid(effect) => effect.render(target),
+				Layers::Dot(effect) => effect.render(target),
+			}
+		}
+	}
+
+	fn composite(blend: BlendMode, opacity: u8) -> Framebuffer<Rgb565, WIDTH, HEIGHT> {
+		let mut compositor = Compositor::new([
+			Layer::new(
+				Layers::Solid(Solid(Rgb565::new(10, 20, 10))),
+				BlendMode::Normal,
+				255,
+			),
+			Layer::new(Layers::Dot(Dot(Rgb565::new(20, 40, 20))), blend, opacity),
+		]);
+		compositor.step(Duration::from_millis(10));
+		*compositor.frame()
+	}
+
+	#[test]
+	fn undrawn_pixels_are_transparent() {
+		let frame = composite(BlendMode::Normal, 255);
+		assert_eq!(frame.pixels()[0][0], Rgb565::new(20, 40, 20));
+		assert_eq!(frame.pixels()[0][1], Rgb565::new(10, 20, 10));
+		assert_eq!(
+			frame.pixels()[HEIGHT - 1][WIDTH - 1],
+			Rgb565::new(10, 20, 10)
+		);
+	}
+
+	#[test]
+	fn blend_modes() {
+		let top_left = |blend, opacity| composite(blend, opacity).pixels()[0][0];
+		assert_eq!(top_left(BlendMode::Normal, 0), Rgb565::new(10, 20, 10));
+		assert_eq!(top_left(BlendMode::Add, 255), Rgb565::new(30, 60, 30));
+		assert_eq!(top_left(BlendMode::Multiply, 255), Rgb565::new(6, 12, 6));
+		assert_eq!(top_left(BlendMode::Screen, 255), Rgb565::new(24, 48, 24));
+		assert_eq!(top_left(BlendMode::Lighten, 255), Rgb565::new(20, 40, 20));
+	}
+}
@@ -0,0 +1,496 @@
+//! Test patterns for bringing up a new panel or pin map
+//!
+//! Every pattern is an `Effect`, so it can be dropped in wherever an effect
+//! is normally used. Each one draws a small legend, naming the signal(s)
+//! it exercises, so a wrong pixel can be traced back to a wrong pin.
+
+use crate::{Effect, HEIGHT, WIDTH};
+use core::time::Duration;
+use embedded_graphics::{
+	fonts::{Font6x6, Text},
+	pixelcolor::Rgb565,
+	prelude::*,
+	primitive_style,
+	primitives::Rectangle,
+	text_style,
+};
+
+/// Draw `text` with a black background, so it stays readable on top of the pattern
+fn legend<D: DrawTarget<Rgb565>>(
+	target: &mut D,
+	text: &str,
+	position: Point,
+) -> Result<(), D::Error> {
+	Text::new(text, position)
+		.into_styled(text_style!(
+			font = Font6x6,
+			text_color = Rgb565::WHITE,
+			background_color = Rgb565::BLACK
+		))
+		.draw(target)
+}
+
+/// Write `value` as decimal digits into the end of `buf`
+fn digits(buf: &mut [u8], mut value: usize) {
+	for b in buf.iter_mut().rev() {
+		*b = b'0' + (value % 10) as u8;
+		value /= 10;
+	}
+}
+
+fn as_str(buf: &[u8]) -> &str {
+	core::str::from_utf8(buf).unwrap_or("")
+}
+
+fn fill<D: DrawTarget<Rgb565>>(
+	target: &mut D,
+	top_left: Point,
+	bottom_right: Point,
+	colour: Rgb565,
+) -> Result<(), D::Error> {
+	Rectangle::new(top_left, bottom_right)
+		.into_styled(primitive_style!(fill_color = colour))
+		.draw(target)
+}
+
+/// Counts how many fixed intervals have passed, to advance a pattern at a steady pace
+struct Ticker {
+	interval: Duration,
+	elapsed:  Duration,
+}
+
+impl Ticker {
+	const fn new(interval: Duration) -> Self {
+		Ticker {
+			interval,
+			elapsed: Duration::from_secs(0),
+		}
+	}
+
+	fn advance(&mut self, delta: Duration) -> usize {
+		self.elapsed += delta;
+		let mut ticks = 0;
+		while self.elapsed >= self.interval {
+			self.elapsed -= self.interval;
+			ticks += 1;
+		}
+		ticks
+	}
+}
+
+/// A single white pixel, moving through the panel in scan order
+///
+/// Every tick shifts it by one clock, so gaps or jumps point at `CLK` or `LAT`.
+pub struct WalkingPixel {
+	ticker:   Ticker,
+	position: usize,
+}
+
+impl WalkingPixel {
+	pub fn new() -> Self {
+		WalkingPixel {
+			ticker:   Ticker::new(Duration::from_millis(20)),
+			position: 0,
+		}
+	}
+}
+
+impl Default for WalkingPixel {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Effect for WalkingPixel {
+	fn step(&mut self, delta: Duration) {
+		let ticks = self.ticker.advance(delta);
+		self.position = (self.position + ticks) % (WIDTH * HEIGHT);
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		target.clear(Rgb565::BLACK)?;
+
+		let x = self.position % WIDTH;
+		let y = self.position / WIDTH;
+		target.draw_pixel(Pixel(Point::new(x as i32, y as i32), Rgb565::WHITE))?;
+
+		// "CLK xx,yy"
+		let mut text = *b"CLK 00,00";
+		digits(&mut text[4..6], x);
+		digits(&mut text[7..9], y);
+		legend(
+			target,
+			as_str(&text),
+			Point::new(0, if y < 8 { 24 } else { 0 }),
+		)?;
+
+		Ok(())
+	}
+}
+
+/// Lights one full row at a time, top to bottom
+///
+/// Exercises the address lines `A`-`D` and the split between the upper
+/// (`R1`, `G1`, `B1`) and lower (`R2`, `G2`, `B2`) half.
+pub struct RowSweep {
+	ticker: Ticker,
+	row:    usize,
+}
+
+impl RowSweep {
+	pub fn new() -> Self {
+		RowSweep {
+			ticker: Ticker::new(Duration::from_millis(250)),
+			row:    0,
+		}
+	}
+}
+
+impl Default for RowSweep {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Effect for RowSweep {
+	fn step(&mut self, delta: Duration) {
+		let ticks = self.ticker.advance(delta);
+		self.row = (self.row + ticks) % HEIGHT;
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		target.clear(Rgb565::BLACK)?;
+
+		let y = self.row as i32;
+		fill(
+			target,
+			Point::new(0, y),
+			Point::new(WIDTH as i32 - 1, y),
+			Rgb565::WHITE,
+		)?;
+
+		// "ROW yy"
+		let mut text = *b"ROW 00";
+		digits(&mut text[4..6], self.row);
+		legend(
+			target,
+			as_str(&text),
+			Point::new(0, if y < 8 { 24 } else { 0 }),
+		)?;
+
+		Ok(())
+	}
+}
+
+/// Lights one full column at a time, left to right
+///
+/// A column that shows up in the wrong place, or twice, points at `CLK`.
+pub struct ColumnSweep {
+	ticker: Ticker,
+	column: usize,
+}
+
+impl ColumnSweep {
+	pub fn new() -> Self {
+		ColumnSweep {
+			ticker: Ticker::new(Duration::from_millis(125)),
+			column: 0,
+		}
+	}
+}
+
+impl Default for ColumnSweep {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Effect for ColumnSweep {
+	fn step(&mut self, delta: Duration) {
+		let ticks = self.ticker.advance(delta);
+		self.column = (self.column + ticks) % WIDTH;
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		target.clear(Rgb565::BLACK)?;
+
+		let x = self.column as i32;
+		fill(
+			target,
+			Point::new(x, 0),
+			Point::new(x, HEIGHT as i32 - 1),
+			Rgb565::WHITE,
+		)?;
+
+		// "COL" above "xx", narrow enough to always fit beside the column
+		let mut text = *b"00";
+		digits(&mut text, self.column);
+		let left = if x < 20 { WIDTH as i32 - 18 } else { 0 };
+		legend(target, "COL", Point::new(left, 0))?;
+		legend(target, as_str(&text), Point::new(left, 6))?;
+
+		Ok(())
+	}
+}
+
+/// Eight vertical bars, covering every combination of the red, green and blue pins
+///
+/// From left to right: white, yellow, cyan, green, magenta, red, blue, black.
+/// Swapped colour pins show up as bars in the wrong order.
+pub struct ColourBars;
+
+impl ColourBars {
+	pub fn new() -> Self {
+		ColourBars
+	}
+}
+
+impl Default for ColourBars {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Effect for ColourBars {
+	fn step(&mut self, _delta: Duration) {}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		const BARS: [Rgb565; 8] = [
+			Rgb565::WHITE,
+			Rgb565::YELLOW,
+			Rgb565::CYAN,
+			Rgb565::GREEN,
+			Rgb565::MAGENTA,
+			Rgb565::RED,
+			Rgb565::BLUE,
+			Rgb565::BLACK,
+		];
+		const BAR_WIDTH: i32 = WIDTH as i32 / BARS.len() as i32;
+
+		for (i, colour) in BARS.iter().enumerate() {
+			let left = i as i32 * BAR_WIDTH;
+			fill(
+				target,
+				Point::new(left, 0),
+				Point::new(left + BAR_WIDTH - 1, HEIGHT as i32 - 1),
+				*colour,
+			)?;
+		}
+
+		legend(target, "R1G1B1", Point::zero())?;
+		legend(target, "R2G2B2", Point::new(0, HEIGHT as i32 / 2))?;
+
+		Ok(())
+	}
+}
+
+/// A horizontal ramp from black to full brightness, one channel at a time
+///
+/// Switches between red, green, blue and white every two seconds.
+/// Exercises the colour pins of both halves and the brightness PWM.
+pub struct Gradient {
+	ticker:  Ticker,
+	channel: usize,
+}
+
+impl Gradient {
+	pub fn new() -> Self {
+		Gradient {
+			ticker:  Ticker::new(Duration::from_secs(2)),
+			channel: 0,
+		}
+	}
+}
+
+impl Default for Gradient {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Effect for Gradient {
+	fn step(&mut self, delta: Duration) {
+		let ticks = self.ticker.advance(delta);
+		self.channel = (self.channel + ticks) % 4;
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		let channel = self.channel;
+
+		for x in 0..WIDTH {
+			let value = (x * 255 / (WIDTH - 1)) as u8;
+			let (r, g, b) = (value >> 3, value >> 2, value >> 3);
+			let colour = match channel {
+				0 => Rgb565::new(r, 0, 0),
+				1 => Rgb565::new(0, g, 0),
+				2 => Rgb565::new(0, 0, b),
+				_ => Rgb565::new(r, g, b),
+			};
+			fill(
+				target,
+				Point::new(x as i32, 0),
+				Point::new(x as i32, HEIGHT as i32 - 1),
+				colour,
+			)?;
+		}
+
+		let text = match channel {
+			0 => "R1R2 PWM",
+			1 => "G1G2 PWM",
+			2 => "B1B2 PWM",
+			_ => "RGB PWM",
+		};
+		legend(target, text, Point::zero())?;
+
+		Ok(())
+	}
+}
+
+/// Lights the two rows sharing one address, upper in red and lower in blue
+///
+/// The legend shows the levels of the address lines for the current rows,
+/// so a stuck or swapped line shows up as rows lighting at the wrong address.
+pub struct AddressTest {
+	ticker:  Ticker,
+	address: usize,
+}
+
+impl AddressTest {
+	pub fn new() -> Self {
+		AddressTest {
+			ticker:  Ticker::new(Duration::from_millis(500)),
+			address: 0,
+		}
+	}
+}
+
+impl Default for AddressTest {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Effect for AddressTest {
+	fn step(&mut self, delta: Duration) {
+		let ticks = self.ticker.advance(delta);
+		self.address = (self.address + ticks) % (HEIGHT / 2);
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		target.clear(Rgb565::BLACK)?;
+
+		let upper = self.address as i32;
+		let lower = upper + HEIGHT as i32 / 2;
+		fill(
+			target,
+			Point::new(0, upper),
+			Point::new(WIDTH as i32 - 1, upper),
+			Rgb565::RED,
+		)?;
+		fill(
+			target,
+			Point::new(0, lower),
+			Point::new(WIDTH as i32 - 1, lower),
+			Rgb565::BLUE,
+		)?;
+
+		// "A0B0C0D0"
+		let mut text = *b"A0B0C0D0";
+		for bit in 0..4 {
+			if self.address & (1 << bit) != 0 {
+				text[bit * 2 + 1] = b'1';
+			}
+		}
+		legend(
+			target,
+			as_str(&text),
+			Point::new(0, if upper < 8 { 8 } else { 0 }),
+		)?;
+
+		Ok(())
+	}
+}
+
+/// A one pixel checkerboard, inverting twice a second
+///
+/// Dim copies of lit pixels in neighbouring rows (ghosting) mean the row
+/// is switched while the output is still enabled, check `OE` and `LAT`.
+pub struct Checkerboard {
+	ticker: Ticker,
+	phase:  bool,
+}
+
+impl Checkerboard {
+	pub fn new() -> Self {
+		Checkerboard {
+			ticker: Ticker::new(Duration::from_millis(500)),
+			phase:  false,
+		}
+	}
+}
+
+impl Default for Checkerboard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Effect for Checkerboard {
+	fn step(&mut self, delta: Duration) {
+		let ticks = self.ticker.advance(delta);
+		self.phase ^= ticks % 2 == 1;
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		for y in 0..HEIGHT {
+			for x in 0..WIDTH {
+				let lit = ((x + y) % 2 == 0) != self.phase;
+				let colour = if lit { Rgb565::WHITE } else { Rgb565::BLACK };
+				target.draw_pixel(Pixel(Point::new(x as i32, y as i32), colour))?;
+			}
+		}
+
+		legend(target, "OE LAT", Point::zero())?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn digits_fill_the_buffer() {
+		let mut buf = *b"CLK 00";
+		digits(&mut buf[4..6], 7);
+		assert_eq!(as_str(&buf), "CLK 07");
+		digits(&mut buf[4..6], 42);
+		assert_eq!(as_str(&buf), "CLK 42");
+		// only the lowest digits fit
+		digits(&mut buf[4..6], 123);
+		assert_eq!(as_str(&buf), "CLK 23");
+	}
+
+	#[test]
+	fn ticker_counts_every_interval() {
+		let mut ticker = Ticker::new(Duration::from_millis(20));
+		assert_eq!(ticker.advance(Duration::from_millis(10)), 0);
+		assert_eq!(ticker.advance(Duration::from_millis(10)), 1);
+		// several ticks in one step
+		assert_eq!(ticker.advance(Duration::from_millis(65)), 3);
+		// the 5ms left over count towards the next one
+		assert_eq!(ticker.advance(Duration::from_millis(14)), 0);
+		assert_eq!(ticker.advance(Duration::from_millis(1)), 1);
+	}
+
+	#[test]
+	fn walking_pixel_wraps_around() {
+		let mut pixel = WalkingPixel::new();
+		// 20ms per pixel
+		pixel.step(Duration::from_millis(20) * (WIDTH * HEIGHT - 1) as u32);
+		assert_eq!(pixel.position, WIDTH * HEIGHT - 1);
+		pixel.step(Duration::from_millis(40));
+		assert_eq!(pixel.position, 1);
+	}
+}
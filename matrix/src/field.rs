@@ -0,0 +1,165 @@
+//! Smooth fields sampled at a low resolution and stretched over the panel
+//!
+//! Noise and other smooth functions barely change from one pixel to the next,
+//! so sampling them on a coarse grid and interpolating in between looks almost
+//! the same, for a fraction of the work.
+
+use crate::{HEIGHT, WIDTH};
+
+/// How the values between the coarse samples are filled in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+	/// Straight lines between the samples, cheap but with visible creases
+	Bilinear,
+	/// Catmull-Rom splines through the samples, smooth but about 4 times the work
+	Bicubic,
+}
+
+/// A field sampled on a grid of `W` x `H` points, stretched over the panel
+///
+/// The corner samples sit exactly on the corner pixels.
+pub struct Field<const W: usize, const H: usize> {
+	samples:       [[f32; W]; H],
+	interpolation: Interpolation,
+}
+
+impl<const W: usize, const H: usize> Field<W, H> {
+	pub fn new(interpolation: Interpolation) -> Self {
+		Field {
+			samples: [[0.0; W]; H],
+			interpolation,
+		}
+	}
+
+	pub fn interpolation(&self) -> Interpolation {
+		self.interpolation
+	}
+
+	pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+		self.interpolation = interpolation;
+	}
+
+	/// Sample `f` on the coarse grid, `f` gets the position in pixels
+	pub fn sample<F: FnMut(f32, f32) -> f32>(&mut self, mut f: F) {
+		for (j, row) in self.samples.iter_mut().enumerate() {
+			let y = j as f32 * Self::spacing_y();
+			for (i, sample) in row.iter_mut().enumerate() {
+				*sample = f(i as f32 * Self::spacing_x(), y);
+			}
+		}
+	}
+
+	/// The interpolated value at a position in pixels
+	pub fn get(&self, x: f32, y: f32) -> f32 {
+		let (i, tx) = split(x / Self::spacing_x(), W);
+		let (j, ty) = split(y / Self::spacing_y(), H);
+		match self.interpolation {
+			Interpolation::Bilinear => {
+				let top = lerp(self.at(i, j), self.at(i + 1, j), tx);
+				let bottom = lerp(self.at(i, j + 1), self.at(i + 1, j + 1), tx);
+				lerp(top, bottom, ty)
+			}
+			Interpolation::Bicubic => {
+				let mut rows = [0.0; 4];
+				for (r, row) in rows.iter_mut().enumerate() {
+					let j = j as isize + r as isize - 1;
+					*row = cubic(
+						[
+							self.at_clamped(i as isize - 1, j),
+							self.at_clamped(i as isize, j),
+							self.at_clamped(i as isize + 1, j),
+							self.at_clamped(i as isize + 2, j),
+						],
+						tx,
+					);
+				}
+				cubic(rows, ty)
+			}
+		}
+	}
+
+	/// Interpolate every pixel of the panel, calling `f` with x, y and the value
+	pub fn upsample<F: FnMut(usize, usize, f32)>(&self, mut f: F) {
+		for y in 0..HEIGHT {
+			for x in 0..WIDTH {
+				f(x, y, self.get(x as f32, y as f32));
+			}
+		}
+	}
+
+	fn spacing_x() -> f32 {
+		(WIDTH - 1) as f32 / (W - 1).max(1) as f32
+	}
+
+	fn spacing_y() -> f32 {
+		(HEIGHT - 1) as f32 / (H - 1).max(1) as f32
+	}
+
+	fn at(&self, i: usize, j: usize) -> f32 {
+		self.samples[j.min(H - 1)][i.min(W - 1)]
+	}
+
+	fn at_clamped(&self, i: isize, j: isize) -> f32 {
+		self.at(i.max(0) as usize, j.max(0) as usize)
+	}
+}
+
+/// Split a position on the coarse grid into the sample before it and the fraction
+/// towards the next one
+fn split(position: f32, samples: usize) -> (usize, f32) {
+	let position = position.max(0.0).min((samples - 1) as f32);
+	let index = (position as usize).min(samples.saturating_sub(2));
+	(index, position - index as f32)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+	a + (b - a) * t
+}
+
+/// Catmull-Rom spline between `p[1]` and `p[2]`
+fn cubic(p: [f32; 4], t: f32) -> f32 {
+	let a = -0.5 * p[0] + 1.5 * p[1] - 1.5 * p[2] + 0.5 * p[3];
+	let b = p[0] - 2.5 * p[1] + 2.0 * p[2] - 0.5 * p[3];
+	let c = -0.5 * p[0] + 0.5 * p[2];
+	((a * t + b) * t + c) * t + p[1]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sampled(interpolation: Interpolation) -> Field<4, 3> {
+		let mut field = Field::new(interpolation);
+		field.sample(|x, y| x + 100.0 * y);
+		field
+	}
+
+	#[test]
+	fn corners_are_exact() {
+		let (right, bottom) = ((WIDTH - 1) as f32, (HEIGHT - 1) as f32);
+		for &interpolation in &[Interpolation::Bilinear, Interpolation::Bicubic] {
+			let field = sampled(interpolation);
+			assert_eq!(field.get(0.0, 0.0), 0.0);
+			assert_eq!(field.get(right, 0.0), right);
+			assert_eq!(field.get(0.0, bottom), 100.0 * bottom);
+			assert_eq!(field.get(right, bottom), right + 100.0 * bottom);
+		}
+	}
+
+	#[test]
+	fn bilinear_reproduces_linear_fields() {
+		let field = sampled(Interpolation::Bilinear);
+		let mut error: f32 = 0.0;
+		field.upsample(|x, y, value| {
+			error = error.max((value - (x as f32 + 100.0 * y as f32)).abs());
+		});
+		assert!(error < 1e-3, "{}", error);
+	}
+
+	#[test]
+	fn outside_is_clamped() {
+		let field = sampled(Interpolation::Bicubic);
+		assert_eq!(field.get(-10.0, -10.0), 0.0);
+		assert_eq!(field.get(1000.0, 0.0), (WIDTH - 1) as f32);
+	}
+}
@@ -0,0 +1,160 @@
+use core::convert::Infallible;
+use embedded_graphics::{
+	drawable::Pixel,
+	geometry::Size,
+	pixelcolor::PixelColor,
+	prelude::*,
+	DrawTarget,
+};
+
+/// An off-screen buffer of `W` x `H` pixels, stored row by row
+///
+/// Draw into it like into the panel, then copy it over with `render` or,
+/// much faster, `Hub75::blit`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Framebuffer<C, const W: usize, const H: usize> {
+	pixels: [[C; W]; H],
+}
+
+impl<C: PixelColor, const W: usize, const H: usize> Framebuffer<C, W, H> {
+	/// Create a buffer with every pixel set to `colour`
+	pub fn new(colour: C) -> Self {
+		Framebuffer {
+			pixels: [[colour; W]; H],
+		}
+	}
+
+	/// The colour of a pixel, `None` if it's outside of the buffer
+	pub fn get_pixel(&self, point: Point) -> Option<C> {
+		if point.x < 0 || point.y < 0 || point.x >= W as i32 || point.y >= H as i32 {
+			return None;
+		}
+		Some(self.pixels[point.y as usize][point.x as usize])
+	}
+
+	/// Set a pixel, ignoring pixels outside of the buffer
+	pub fn set_pixel(&mut self, point: Point, colour: C) {
+		if point.x < 0 || point.y < 0 || point.x >= W as i32 || point.y >= H as i32 {
+			return;
+		}
+		self.pixels[point.y as usize][point.x as usize] = colour;
+	}
+
+	/// The pixels, indexed as `[y][x]`
+	pub fn pixels(&self) -> &[[C; W]; H] {
+		&self.pixels
+	}
+
+	/// The pixels, indexed as `[y][x]`
+	pub fn pixels_mut(&mut self) -> &mut [[C; W]; H] {
+		&mut self.pixels
+	}
+
+	/// Set every pixel to `colour`
+	pub fn fill(&mut self, colour: C) {
+		self.pixels = [[colour; W]; H];
+	}
+
+	/// All pixels row by row, from the top left
+	pub fn iter(&self) -> impl Iterator<Item = Pixel<C>> + '_ {
+		self.pixels.iter().enumerate().flat_map(|(y, row)| {
+			row.iter()
+				.enumerate()
+				.map(move |(x, &colour)| Pixel(Point::new(x as i32, y as i32), colour))
+		})
+	}
+
+	/// Draw the whole buffer to `target`
+	pub fn render<D: DrawTarget<C>>(&self, target: &mut D) -> Result<(), D::Error> {
+		target.draw_iter(self.iter())
+	}
+}
+
+impl<C: PixelColor, const W: usize, const H: usize> DrawTarget<C> for Framebuffer<C, W, H> {
+	type Error = Infallible;
+
+	fn draw_pixel(&mut self, item: Pixel<C>) -> Result<(), Self::Error> {
+		let Pixel(point, colour) = item;
+		self.set_pixel(point, colour);
+		Ok(())
+	}
+
+	fn size(&self) -> Size {
+		Size::new(W as u32, H as u32)
+	}
+
+	fn clear(&mut self, colour: C) -> Result<(), Self::Error> {
+		self.fill(colour);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+
+	type Frame = Framebuffer<Rgb888, 4, 3>;
+
+	#[test]
+	fn pixels_outside_are_ignored() {
+		let mut frame = Frame::new(Rgb888::BLACK);
+		frame.set_pixel(Point::new(3, 2), Rgb888::RED);
+		assert_eq!(frame.get_pixel(Point::new(3, 2)), Some(Rgb888::RED));
+
+		for &point in &[
+			Point::new(-1, 0),
+			Point::new(0, -1),
+			Point::new(4, 0),
+			Point::new(0, 3),
+		] {
+			frame.set_pixel(point, Rgb888::GREEN);
+			assert_eq!(frame.get_pixel(point), None);
+		}
+		assert!(frame
+			.pixels()
+			.iter()
+			.flatten()
+			.all(|&pixel| pixel != Rgb888::GREEN));
+	}
+
+	#[test]
+	fn iter_goes_row_by_row() {
+		let mut frame = Frame::new(Rgb888::BLACK);
+		frame.set_pixel(Point::new(1, 0), Rgb888::RED);
+		let point = |i| frame.iter().nth(i).map(|Pixel(point, _)| point);
+		assert_eq!(point(0), Some(Point::new(0, 0)));
+		assert_eq!(point(1), Some(Point::new(1, 0)));
+		assert_eq!(point(4), Some(Point::new(0, 1)));
+		assert_eq!(point(11), Some(Point::new(3, 2)));
+		assert_eq!(point(12), None);
+		assert_eq!(
+			frame.iter().nth(1),
+			Some(Pixel(Point::new(1, 0), Rgb888::RED))
+		);
+	}
+
+	#[test]
+	fn draw_target_clips() {
+		let mut frame = Frame::new(Rgb888::BLACK);
+		frame
+			.draw_iter(
+				[
+					Pixel(Point::new(-1, 1), Rgb888::RED),
+					Pixel(Point::new(2, 1), Rgb888::GREEN),
+					Pixel(Point::new(4, 1), Rgb888::RED),
+					Pixel(Point::new(2, 3), Rgb888::RED),
+				]
+				.iter()
+				.copied(),
+			)
+			.unwrap();
+		assert_eq!(frame.get_pixel(Point::new(2, 1)), Some(Rgb888::GREEN));
+		assert!(frame
+			.pixels()
+			.iter()
+			.flatten()
+			.all(|&pixel| pixel != Rgb888::RED));
+		assert_eq!(frame.size(), Size::new(4, 3));
+	}
+}
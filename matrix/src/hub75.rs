@@ -15,10 +15,8 @@ use embedded_hal::{blocking::delay::DelayUs, digital::v2::OutputPin};
 /// The display doesn't really do brightness, so we have to do it ourselves, by
 /// rendering the same frame multiple times, with some pixels being turned of if
 /// they are darker (pwm)
-
 pub struct Hub75<PINS> {
-	//       r1, g1, b1, r2, g2, b2, column, row
-	data:             [[(u8, u8, u8, u8, u8, u8); 64]; 16],
+	scan:             Scan,
 	brightness_step:  u8,
 	brightness_count: u8,
 	pins:             PINS,
@@ -140,11 +138,10 @@ impl<PINS: Outputs> Hub75<PINS> {
 	/// 3-4 bits are usually a good choice.
 	pub fn new(pins: PINS, brightness_bits: u8) -> Self {
 		assert!(brightness_bits < 9 && brightness_bits > 0);
-		let data = [[(0, 0, 0, 0, 0, 0); 64]; 16];
 		let brightness_step = 1 << (8 - brightness_bits);
 		let brightness_count = ((1 << brightness_bits as u16) - 1) as u8;
 		Self {
-			data,
+			scan: Scan::new(),
 			brightness_step,
 			brightness_count,
 			pins,
@@ -162,7 +159,7 @@ impl<PINS: Outputs> Hub75<PINS> {
 		// PWM cycle
 		for mut brightness in 0..self.brightness_count {
 			brightness = (brightness + 1).saturating_mul(self.brightness_step);
-			for (count, row) in self.data.iter().enumerate() {
+			for (count, row) in self.scan.data.iter().enumerate() {
 				self.pins.oe().set_low().ok();
 				for element in row.iter() {
 					if element.0 >= brightness {
@@ -248,7 +245,7 @@ impl<PINS: Outputs> Hub75<PINS> {
 	/// It's a bit faster than using the embedded_graphics interface
 	/// to do the same
 	pub fn clear(&mut self) {
-		for row in self.data.iter_mut() {
+		for row in self.scan.data.iter_mut() {
 			for e in row.iter_mut() {
 				e.0 = 0;
 				e.1 = 0;
@@ -261,49 +258,149 @@ impl<PINS: Outputs> Hub75<PINS> {
 	}
 }
 
-use embedded_graphics::{drawable::Pixel, geometry::Size, prelude::*, DrawTarget};
+use crate::{
+	colour::{correction, HSV},
+	framebuffer::Framebuffer,
+	math8::scale8,
+};
+use embedded_graphics::{
+	drawable::Pixel,
+	geometry::Size,
+	pixelcolor::Rgb888,
+	prelude::*,
+	DrawTarget,
+};
 
-impl<PINS: Outputs, C: RgbColor> DrawTarget<C> for Hub75<PINS> {
-	type Error = core::convert::Infallible;
+// This table remaps linear input values
+// (the numbers we’d like to use; e.g. 127 = half brightness)
+// to nonlinear gamma-corrected output values
+// (numbers producing the desired effect on the LED;
+// e.g. 36 = half brightness).
+const GAMMA8: [u8; 256] = [
+	0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+	1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+	5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+	14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+	27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+	47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+	73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+	105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+	138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+	177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+	223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
 
-	fn draw_pixel(&mut self, item: Pixel<C>) -> Result<(), Self::Error> {
-		// This table remaps linear input values
-		// (the numbers we’d like to use; e.g. 127 = half brightness)
-		// to nonlinear gamma-corrected output values
-		// (numbers producing the desired effect on the LED;
-		// e.g. 36 = half brightness).
-		const GAMMA8: [u8; 256] = [
-			0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-			1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4,
-			4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11,
-			12, 12, 13, 13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22,
-			22, 23, 24, 24, 25, 25, 26, 27, 27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37,
-			38, 39, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58,
-			59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72, 73, 74, 75, 77, 78, 79, 81, 82, 83, 85,
-			86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104, 105, 107, 109, 110, 112, 114,
-			115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137, 138, 140, 142, 144,
-			146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175, 177, 180,
-			182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
-			223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
-		];
+// r1, g1, b1, r2, g2, b2, one column of a row in the upper and lower half
+type Pair = (u8, u8, u8, u8, u8, u8);
 
-		let Pixel(coord, color) = item;
-		if coord[0] < 0 || coord[0] >= 64 || coord[1] < 0 || coord[1] >= 32 {
-			return Ok(());
+/// A frame, converted to what `Hub75::output` shifts out
+///
+/// Converting a frame takes a lot longer than copying one. When the
+/// refresh runs in an interrupt, a frame can be converted into a `Scan`
+/// without holding the panel, and then handed over with `Hub75::swap`.
+#[derive(Copy, Clone)]
+pub struct Scan {
+	data: [[Pair; 64]; 16],
+}
+
+impl Scan {
+	pub fn new() -> Self {
+		Scan {
+			data: [[(0, 0, 0, 0, 0, 0); 64]; 16],
 		}
+	}
 
-		let row = coord[1] % 16;
-		let data = &mut self.data[row as usize][coord[0] as usize];
-		if coord[1] >= 16 {
-			data.3 = GAMMA8[color.r() as usize * 255 / C::MAX_R as usize];
-			data.4 = GAMMA8[color.g() as usize * 255 / C::MAX_G as usize];
-			data.5 = GAMMA8[color.b() as usize * 255 / C::MAX_B as usize];
+	fn store(&mut self, x: usize, y: usize, r: u8, g: u8, b: u8, adjustment: Rgb888) {
+		let r = GAMMA8[scale8(r, adjustment.r()) as usize];
+		let g = GAMMA8[scale8(g, adjustment.g()) as usize];
+		let b = GAMMA8[scale8(b, adjustment.b()) as usize];
+
+		let data = &mut self.data[y % 16][x];
+		if y >= 16 {
+			data.3 = r;
+			data.4 = g;
+			data.5 = b;
 		} else {
-			data.0 = GAMMA8[color.r() as usize * 255 / C::MAX_R as usize];
-			data.1 = GAMMA8[color.g() as usize * 255 / C::MAX_G as usize];
-			data.2 = GAMMA8[color.b() as usize * 255 / C::MAX_B as usize];
+			data.0 = r;
+			data.1 = g;
+			data.2 = b;
+		}
+	}
+
+	/// Convert a whole frame, with the installed colour correction
+	pub fn blit<C: PixelColor>(&mut self, frame: &Framebuffer<C, 64, 32>)
+	where
+		Rgb888: From<C>,
+	{
+		let adjustment = correction::current_adjustment();
+		for (y, row) in frame.pixels().iter().enumerate() {
+			for (x, &colour) in row.iter().enumerate() {
+				let rgb = Rgb888::from(colour);
+				self.store(x, y, rgb.r(), rgb.g(), rgb.b(), adjustment);
+			}
 		}
+	}
+}
+
+impl Default for Scan {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<PINS: Outputs> Hub75<PINS> {
+	/// Store a pixel, with 8 bits per channel
+	///
+	/// `adjustment` is the installed colour correction, applied before the
+	/// gamma correction. It's loaded once per draw instead of for every pixel.
+	fn set(&mut self, coord: Point, r: u8, g: u8, b: u8, adjustment: Rgb888) {
+		if coord[0] < 0 || coord[0] >= 64 || coord[1] < 0 || coord[1] >= 32 {
+			return;
+		}
+		self.scan
+			.store(coord[0] as usize, coord[1] as usize, r, g, b, adjustment);
+	}
+
+	/// Copy a whole frame, much faster than drawing it pixel by pixel
+	pub fn blit<C: PixelColor>(&mut self, frame: &Framebuffer<C, 64, 32>)
+	where
+		Rgb888: From<C>,
+	{
+		self.scan.blit(frame);
+	}
 
+	/// Show `scan` from now on, handing back the frame shown until now
+	///
+	/// Only copies the converted frame, which is much faster than `blit`.
+	pub fn swap(&mut self, scan: &mut Scan) {
+		core::mem::swap(&mut self.scan, scan);
+	}
+}
+
+fn to_rgb888<C: RgbColor>(color: C) -> Rgb888 {
+	Rgb888::new(
+		(color.r() as usize * 255 / C::MAX_R as usize) as u8,
+		(color.g() as usize * 255 / C::MAX_G as usize) as u8,
+		(color.b() as usize * 255 / C::MAX_B as usize) as u8,
+	)
+}
+
+impl<PINS: Outputs, C: RgbColor> DrawTarget<C> for Hub75<PINS> {
+	type Error = core::convert::Infallible;
+
+	fn draw_pixel(&mut self, item: Pixel<C>) -> Result<(), Self::Error> {
+		self.draw_iter(core::iter::once(item))
+	}
+
+	fn draw_iter<T>(&mut self, item: T) -> Result<(), Self::Error>
+	where
+		T: IntoIterator<Item = Pixel<C>>,
+	{
+		let adjustment = correction::current_adjustment();
+		for Pixel(coord, color) in item {
+			let rgb = to_rgb888(color);
+			self.set(coord, rgb.r(), rgb.g(), rgb.b(), adjustment);
+		}
 		Ok(())
 	}
 
@@ -312,3 +409,28 @@ impl<PINS: Outputs, C: RgbColor> DrawTarget<C> for Hub75<PINS> {
 		Size::new(64, 32)
 	}
 }
+
+/// Converted with `hsv2rgb_rainbow` and the current `HsvConfig`
+impl<PINS: Outputs> DrawTarget<HSV> for Hub75<PINS> {
+	type Error = core::convert::Infallible;
+
+	fn draw_pixel(&mut self, item: Pixel<HSV>) -> Result<(), Self::Error> {
+		self.draw_iter(core::iter::once(item))
+	}
+
+	fn draw_iter<T>(&mut self, item: T) -> Result<(), Self::Error>
+	where
+		T: IntoIterator<Item = Pixel<HSV>>,
+	{
+		let adjustment = correction::current_adjustment();
+		for Pixel(coord, color) in item {
+			let rgb = Rgb888::from(color);
+			self.set(coord, rgb.r(), rgb.g(), rgb.b(), adjustment);
+		}
+		Ok(())
+	}
+
+	fn size(&self) -> Size {
+		Size::new(64, 32)
+	}
+}
@@ -1,5 +1,5 @@
 use crate::{colour::HSV, HEIGHT, WIDTH};
-use embedded_graphics::{drawable::*, pixelcolor::Rgb565, prelude::Point};
+use embedded_graphics::{drawable::*, prelude::Point};
 
 pub struct MatrixIter<'a> {
 	x:      usize,
@@ -8,13 +8,13 @@ pub struct MatrixIter<'a> {
 }
 
 impl<'a> MatrixIter<'a> {
-	pub fn new(matrix: &'a [[HSV; HEIGHT]; WIDTH]) -> MatrixIter {
+	pub fn new(matrix: &'a [[HSV; HEIGHT]; WIDTH]) -> MatrixIter<'a> {
 		MatrixIter { x: 0, y: 0, matrix }
 	}
 }
 
 impl Iterator for MatrixIter<'_> {
-	type Item = Pixel<Rgb565>;
+	type Item = Pixel<HSV>;
 
 	fn next(&mut self) -> Option<Self::Item> {
 		// sprintln!("Next");
@@ -29,7 +29,7 @@ impl Iterator for MatrixIter<'_> {
 		// sprintln!("Inner {}:{}", self.x, self.y);
 		let pixel = Pixel(
 			Point::new(self.x as i32, self.y as i32),
-			self.matrix[self.x][self.y].into(),
+			self.matrix[self.x][self.y],
 		);
 
 		self.x += 1;
@@ -1,263 +1,377 @@
 #![no_std]
 
+pub mod any;
 pub mod colour;
+pub mod compositor;
+pub mod diagnostics;
+pub mod field;
+pub mod framebuffer;
 pub mod hub75;
 pub mod iter;
+pub mod math8;
 pub mod noise;
-
-use colour::HSV;
-use core::ops::DerefMut;
+pub mod ops;
+pub mod params;
+pub mod playlist;
+pub mod runner;
+pub mod shader;
+pub mod transition;
+
+use any::AnyEffect;
+use colour::{
+	palette::{self, Palette},
+	HSV,
+};
+use core::time::Duration;
 use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitive_style, primitives::Rectangle};
-use embedded_hal::blocking::delay::DelayUs;
-use hub75::{Hub75, Outputs};
-use iter::MatrixIter;
-use noise::simplex;
+use field::{Field, Interpolation};
+use framebuffer::Framebuffer;
+use math8::{cos16, sin16};
+use noise::{
+	grid::{simplex_grid, Grid},
+	inoise8,
+	simplex,
+};
+use params::{Param, ParamError, ParamKind, ParamValue};
 
 const WIDTH: usize = 64;
 const HEIGHT: usize = 32;
 
-pub fn effect_sched<OUT: Outputs>() -> impl FnMut(&mut Hub75<OUT>) {
-	// let mut draw = rects();
-	let mut draw = cloud();
-	return draw;
-}
-
-pub fn effect<OUT: Outputs>(mut matrix: Hub75<OUT>, mut delay: impl DelayUs<u8>) -> ! {
-	// let mut draw = rects();
-	let mut draw = cloud();
-	loop {
-		draw(&mut matrix);
-		matrix.output(&mut delay);
-		// sprintln!("Loop");
-	}
-}
-
-fn _base<T: Outputs>() -> fn(&mut Hub75<T>) {
-	return |_matrix| {};
-}
-
-fn cloud<T: Outputs>() -> impl FnMut(&mut Hub75<T>) {
-	let mut matrix_data = [[HSV::default(); HEIGHT]; WIDTH];
-
-	let mut sin: u8 = 0;
-	let mut hue: u8 = 0;
-
-	// const CYCLE_LENGTH: usize = 16;
-
-	let mut x_pos: f32 = 0.0;
-	let mut y_pos: f32 = 0.0;
-
-	let draw = move |matrix: &mut Hub75<T>| {
-		// sprintln!("1");
-		sin = sin + 1;
-		hue = hue + 2;
-
-		// sprintln!("2");
-		let thing = sin as f32 / 255.0 * 2.0 * core::f32::consts::PI;
-		x_pos += libm::sinf(thing);
-		y_pos += libm::cosf(thing);
-
-		// sprintln!("3");
-		for x in 0..WIDTH {
-			// sprintln!("Loomp");
-			for y in 0..HEIGHT {
-				// let noise_val= (noise((x as f32 + x_pos as f32) / 4.0, (y as f32 + y_pos as f32) / 4.0, 0.0) * 255.0) as u8;
-				let noise_val = (simplex(
-					(x as f32 + x_pos as f32 / 5.0) / 4.0,
-					(y as f32) / 4.0,
-					y_pos as f32 / 512.0,
-				) * 255.0) as u8;
-				let colour = HSV::new(noise_val + hue, noise_val, 255 /* noise_val */);
-				matrix_data[x][y] = colour;
-				// sprintln!("{:?} {:?}",	colour, hsv2rgb_rainbow(colour));
-			}
-		}
-		// sprintln!("4");
-		matrix.draw_iter(MatrixIter::new(&matrix_data)).unwrap();
-	};
-
-	return draw;
-}
-
+/// Drifting simplex noise, mapped to a slowly rotating rainbow or palette
 pub struct CloudEffect {
-	matrix_data: [[HSV; HEIGHT]; WIDTH],
-	sin:         u8,
-	hue:         u8,
-	x_pos:       f32,
-	y_pos:       f32,
+	frame: Framebuffer<Rgb565, WIDTH, HEIGHT>,
+	sin:   f32,
+	hue:   f32,
+	x_pos: f32,
+	y_pos: f32,
+
+	speed:     f32,
+	hue_speed: f32,
+	scale:     f32,
+	drift:     f32,
+	palette:   i32,
+	fixed:     bool,
+	coarse:    bool,
+
+	field: Field<16, 8>,
 }
 
 impl CloudEffect {
+	const PARAMS: &'static [Param] = &[
+		// How fast the clouds move, the original animation stepped at 60 Hz
+		Param {
+			name:    "speed",
+			kind:    ParamKind::Float {
+				min: 0.0,
+				max: 600.0,
+			},
+			default: ParamValue::Float(60.0),
+		},
+		// Hue rotation, in steps of the 256 step colour wheel per second
+		Param {
+			name:    "hue_speed",
+			kind:    ParamKind::Float {
+				min: 0.0,
+				max: 1024.0,
+			},
+			default: ParamValue::Float(120.0),
+		},
+		// Size of the clouds, in pixels per unit of noise
+		Param {
+			name:    "scale",
+			kind:    ParamKind::Float {
+				min: 1.0,
+				max: 32.0,
+			},
+			default: ParamValue::Float(4.0),
+		},
+		// How slowly the clouds change shape while moving
+		Param {
+			name:    "drift",
+			kind:    ParamKind::Float {
+				min: 16.0,
+				max: 4096.0,
+			},
+			default: ParamValue::Float(512.0),
+		},
+		// 0 for the rainbow, otherwise one of `colour::palette::BUILTIN`
+		Param {
+			name:    "palette",
+			kind:    ParamKind::Int {
+				min: 0,
+				max: palette::BUILTIN.len() as i32,
+			},
+			default: ParamValue::Int(0),
+		},
+		// Integer noise instead of f32 simplex, for targets without an FPU
+		Param {
+			name:    "fixed",
+			kind:    ParamKind::Bool,
+			default: ParamValue::Bool(cfg!(feature = "fixed-noise")),
+		},
+		// Sample the f32 noise on a 16x8 grid and interpolate in between
+		Param {
+			name:    "coarse",
+			kind:    ParamKind::Bool,
+			default: ParamValue::Bool(false),
+		},
+	];
+
 	pub fn new() -> Self {
 		CloudEffect {
-			matrix_data: [[HSV::default(); HEIGHT]; WIDTH],
+			frame: Framebuffer::new(Rgb565::BLACK),
 
-			sin: 0,
-			hue: 0,
+			sin: 0.0,
+			hue: 0.0,
 
 			x_pos: 0.0,
 			y_pos: 0.0,
+
+			speed:     60.0,
+			hue_speed: 120.0,
+			scale:     4.0,
+			drift:     512.0,
+			palette:   0,
+			fixed:     cfg!(feature = "fixed-noise"),
+			coarse:    false,
+
+			field: Field::new(Interpolation::Bicubic),
 		}
 	}
 }
 
+impl Default for CloudEffect {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl Effect for CloudEffect {
-	fn step(&mut self) {
-		self.sin = self.sin + 1;
-		self.hue = self.hue + 2;
-
-		// sprintln!("2");
-		let thing = self.sin as f32 / 255.0 * 2.0 * core::f32::consts::PI;
-		self.x_pos += libm::sinf(thing);
-		self.y_pos += libm::cosf(thing);
-
-		// sprintln!("3");
-		for x in 0..WIDTH {
-			// sprintln!("Loomp");
-			for y in 0..HEIGHT {
-				// let noise_val= (noise((x as f32 + x_pos as f32) / 4.0, (y as f32 + y_pos as f32) / 4.0, 0.0) * 255.0) as u8;
-				let noise_val = (simplex(
-					(x as f32 + self.x_pos as f32 / 5.0) / 4.0,
-					(y as f32) / 4.0,
-					self.y_pos as f32 / 512.0,
-				) * 255.0) as u8;
-				let colour = HSV::new(noise_val + self.hue, noise_val, 255 /* noise_val */);
-				self.matrix_data[x][y] = colour;
-				// sprintln!("{:?} {:?}",	colour, hsv2rgb_rainbow(colour));
+	fn step(&mut self, delta: Duration) {
+		let delta = delta.as_secs_f32();
+		self.sin = (self.sin + delta * self.speed) % 256.0;
+		self.hue = (self.hue + delta * self.hue_speed) % 256.0;
+
+		let thing = (self.sin * 256.0) as u16;
+		self.x_pos += sin16(thing) as f32 / 32768.0 * delta * self.speed;
+		self.y_pos += cos16(thing) as f32 / 32768.0 * delta * self.speed;
+
+		let hue = self.hue as u8;
+		let selected = match self.palette {
+			0 => None,
+			index => Some(palette::BUILTIN[index as usize - 1]),
+		};
+		let colour = |noise_val: u8| -> Rgb565 {
+			match selected {
+				Some(palette) => palette.lookup(noise_val.wrapping_add(hue)).into(),
+				None => HSV::new(
+					noise_val.wrapping_add(hue),
+					noise_val,
+					255, /* noise_val */
+				)
+				.into(),
+			}
+		};
+
+		let pixels = self.frame.pixels_mut();
+		if self.fixed {
+			// the same coordinates as below, in 8.8 fixed point
+			let step = (256.0 / self.scale) as u16;
+			let x_base = (self.x_pos / 5.0 / self.scale * 256.0) as i32 as u16;
+			let z = (self.y_pos / self.drift * 256.0) as i32 as u16;
+			for (y, row) in pixels.iter_mut().enumerate() {
+				for (x, pixel) in row.iter_mut().enumerate() {
+					let noise_val =
+						inoise8(x_base.wrapping_add(x as u16 * step), y as u16 * step, z);
+					*pixel = colour(noise_val);
+				}
 			}
+		} else if self.coarse {
+			let (x_pos, y_pos, scale, drift) = (self.x_pos, self.y_pos, self.scale, self.drift);
+			self.field
+				.sample(|x, y| simplex((x + x_pos / 5.0) / scale, y / scale, y_pos / drift));
+			self.field.upsample(|x, y, noise| {
+				pixels[y][x] = colour((noise * 255.0) as u8);
+			});
+		} else {
+			let grid = Grid::new(
+				(self.x_pos / 5.0 / self.scale, 0.0),
+				(1.0 / self.scale, 1.0 / self.scale),
+				self.y_pos / self.drift,
+			);
+			simplex_grid(&grid, WIDTH, HEIGHT, |x, y, noise| {
+				pixels[y][x] = colour((noise * 255.0) as u8);
+			});
 		}
 	}
 
-	fn write<PINS: Outputs>(&self, matrix: &mut Hub75<PINS>) {
-		matrix
-			.draw_iter(MatrixIter::new(&self.matrix_data))
-			.unwrap();
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		self.frame.render(target)
 	}
-}
 
-fn rects<T: Outputs>() -> impl FnMut(&mut Hub75<T>) {
-	let mut thing: f32 = 0.0;
-	let mut speed: f32 = 0.05;
-	return move |matrix| {
-		matrix.clear();
-		let left = thing as i32;
-		let right = thing as i32 + 3;
-
-		Rectangle::new(Point::new(left, 0), Point::new(right, 3))
-			.into_styled(primitive_style!(fill_color = Rgb565::RED))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 4), Point::new(63 - left, 7))
-			.into_styled(primitive_style!(fill_color = Rgb565::GREEN))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(left, 8), Point::new(right, 11))
-			.into_styled(primitive_style!(fill_color = Rgb565::BLUE))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 12), Point::new(63 - left, 15))
-			.into_styled(primitive_style!(fill_color = Rgb565::WHITE))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(left, 16), Point::new(right, 19))
-			.into_styled(primitive_style!(fill_color = Rgb565::RED))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 20), Point::new(63 - left, 23))
-			.into_styled(primitive_style!(fill_color = Rgb565::GREEN))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(left, 24), Point::new(right, 27))
-			.into_styled(primitive_style!(fill_color = Rgb565::BLUE))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 28), Point::new(63 - left, 31))
-			.into_styled(primitive_style!(fill_color = Rgb565::WHITE))
-			.draw(matrix)
-			.unwrap();
-
-		thing += speed;
-		if thing > 64.0 {
-			speed = -speed;
+	fn params(&self) -> &'static [Param] {
+		Self::PARAMS
+	}
+
+	fn get(&self, name: &str) -> Option<ParamValue> {
+		match name {
+			"speed" => Some(ParamValue::Float(self.speed)),
+			"hue_speed" => Some(ParamValue::Float(self.hue_speed)),
+			"scale" => Some(ParamValue::Float(self.scale)),
+			"drift" => Some(ParamValue::Float(self.drift)),
+			"palette" => Some(ParamValue::Int(self.palette)),
+			"fixed" => Some(ParamValue::Bool(self.fixed)),
+			"coarse" => Some(ParamValue::Bool(self.coarse)),
+			_ => None,
 		}
-		if thing < -4.0 {
-			speed = -speed;
+	}
+
+	fn set(&mut self, name: &str, value: ParamValue) -> Result<(), ParamError> {
+		let value = params::check(Self::PARAMS, name, value)?;
+		match name {
+			"speed" => self.speed = value.as_float().unwrap(),
+			"hue_speed" => self.hue_speed = value.as_float().unwrap(),
+			"scale" => self.scale = value.as_float().unwrap(),
+			"drift" => self.drift = value.as_float().unwrap(),
+			"palette" => self.palette = value.as_int().unwrap(),
+			"fixed" => self.fixed = value.as_bool().unwrap(),
+			"coarse" => self.coarse = value.as_bool().unwrap(),
+			_ => unreachable!(),
 		}
-	};
+		Ok(())
+	}
 }
 
-pub fn new_effect() -> impl Effect + Send {
-	RectEffect::new()
+pub fn new_effect() -> AnyEffect {
+	RectEffect::new().into()
 }
 
+/// An animation, independent of the display it ends up on
+///
+/// `step` advances the animation by the time passed since the previous step,
+/// so it runs at the same speed no matter how often it gets called.
+/// `render` draws the current frame to anything that accepts `Rgb565` pixels,
+/// usually a `Hub75`.
+///
+/// Effects can expose parameters to tweak them at runtime, `params` describes them
+/// and `get` and `set` read and change their values by name.
 pub trait Effect {
-	fn step(&mut self);
-	fn write<PINS: Outputs>(&self, matrix: &mut Hub75<PINS>);
+	fn step(&mut self, delta: Duration);
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error>;
+
+	fn params(&self) -> &'static [Param] {
+		&[]
+	}
+
+	fn get(&self, _name: &str) -> Option<ParamValue> {
+		None
+	}
+
+	fn set(&mut self, _name: &str, _value: ParamValue) -> Result<(), ParamError> {
+		Err(ParamError::Unknown)
+	}
 }
 
+/// Rectangles bouncing between the left and right edge
 pub struct RectEffect {
-	thing: f32,
+	thing:     f32,
+	direction: f32,
+
 	speed: f32,
+	size:  i32,
 }
 
 impl RectEffect {
+	const PARAMS: &'static [Param] = &[
+		// Pixels per second
+		Param {
+			name:    "speed",
+			kind:    ParamKind::Float {
+				min: 0.0,
+				max: 256.0,
+			},
+			default: ParamValue::Float(60.0),
+		},
+		// Width and height of the rectangles
+		Param {
+			name:    "size",
+			kind:    ParamKind::Int { min: 1, max: 16 },
+			default: ParamValue::Int(4),
+		},
+	];
+
 	pub fn new() -> Self {
 		RectEffect {
-			thing: 0.0,
-			speed: 1.0,
+			thing:     0.0,
+			direction: 1.0,
+
+			speed: 60.0,
+			size:  4,
 		}
 	}
 }
 
+impl Default for RectEffect {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl Effect for RectEffect {
-	fn step(&mut self) {
-		self.thing += self.speed;
-		if self.thing > 64.0 {
-			self.speed = -self.speed;
+	fn step(&mut self, delta: Duration) {
+		self.thing += self.direction * self.speed * delta.as_secs_f32();
+		if self.thing > WIDTH as f32 {
+			self.direction = -1.0;
 		}
-		if self.thing < -4.0 {
-			self.speed = -self.speed;
+		if self.thing < -self.size as f32 {
+			self.direction = 1.0;
 		}
 	}
 
-	fn write<PINS: Outputs>(&self, mut matrix: &mut Hub75<PINS>) {
-		let matrix = matrix.deref_mut();
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		const COLOURS: [Rgb565; 4] = [Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE, Rgb565::WHITE];
 
-		matrix.clear();
+		target.clear(Rgb565::BLACK)?;
 		let left = self.thing as i32;
-		let right = self.thing as i32 + 3;
-
-		Rectangle::new(Point::new(left, 0), Point::new(right, 3))
-			.into_styled(primitive_style!(fill_color = Rgb565::RED))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 4), Point::new(63 - left, 7))
-			.into_styled(primitive_style!(fill_color = Rgb565::GREEN))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(left, 8), Point::new(right, 11))
-			.into_styled(primitive_style!(fill_color = Rgb565::BLUE))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 12), Point::new(63 - left, 15))
-			.into_styled(primitive_style!(fill_color = Rgb565::WHITE))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(left, 16), Point::new(right, 19))
-			.into_styled(primitive_style!(fill_color = Rgb565::RED))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 20), Point::new(63 - left, 23))
-			.into_styled(primitive_style!(fill_color = Rgb565::GREEN))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(left, 24), Point::new(right, 27))
-			.into_styled(primitive_style!(fill_color = Rgb565::BLUE))
-			.draw(matrix)
-			.unwrap();
-		Rectangle::new(Point::new(63 - right, 28), Point::new(63 - left, 31))
-			.into_styled(primitive_style!(fill_color = Rgb565::WHITE))
-			.draw(matrix)
-			.unwrap();
+		let right = self.thing as i32 + self.size - 1;
+
+		// Every other row of rectangles moves in the opposite direction
+		for (row, top) in (0..HEIGHT as i32).step_by(self.size as usize).enumerate() {
+			let (left, right) = if row % 2 == 0 {
+				(left, right)
+			} else {
+				(WIDTH as i32 - 1 - right, WIDTH as i32 - 1 - left)
+			};
+			Rectangle::new(
+				Point::new(left, top),
+				Point::new(right, top + self.size - 1),
+			)
+			.into_styled(primitive_style!(fill_color = COLOURS[row % COLOURS.len()]))
+			.draw(target)?;
+		}
+
+		Ok(())
+	}
+
+	fn params(&self) -> &'static [Param] {
+		Self::PARAMS
+	}
+
+	fn get(&self, name: &str) -> Option<ParamValue> {
+		match name {
+			"speed" => Some(ParamValue::Float(self.speed)),
+			"size" => Some(ParamValue::Int(self.size)),
+			_ => None,
+		}
+	}
+
+	fn set(&mut self, name: &str, value: ParamValue) -> Result<(), ParamError> {
+		let value = params::check(Self::PARAMS, name, value)?;
+		match name {
+			"speed" => self.speed = value.as_float().unwrap(),
+			"size" => self.size = value.as_int().unwrap(),
+			_ => unreachable!(),
+		}
+		Ok(())
 	}
 }
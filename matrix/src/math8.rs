@@ -0,0 +1,352 @@
+//! Fast 8 and 16 bit integer math, ported from FastLED's `lib8tion`
+//!
+//! Angles and fractions use the full range of the integer type, so for
+//! `sin8` 256 is a full turn, and for `scale8` 255 is (almost) 1.0.
+//! None of these need an FPU.
+
+/// Scale `i` by `scale / 256`
+pub fn scale8(i: u8, scale: u8) -> u8 {
+	(((i as u16) * (1 + scale as u16)) >> 8) as u8
+}
+
+/// Like `scale8`, but never scales a non-zero value down to zero
+pub fn scale8_video(i: u8, scale: u8) -> u8 {
+	(((i as usize * scale as usize) >> 8) + if i > 0 && scale > 0 { 1 } else { 0 }) as u8
+}
+
+/// Scale `i` by `scale / 65536`
+pub fn scale16(i: u16, scale: u16) -> u16 {
+	(((i as u32) * (1 + scale as u32)) >> 16) as u16
+}
+
+/// Add, saturating at 255
+pub fn qadd8(i: u8, j: u8) -> u8 {
+	i.saturating_add(j)
+}
+
+/// Subtract, saturating at 0
+pub fn qsub8(i: u8, j: u8) -> u8 {
+	i.saturating_sub(j)
+}
+
+/// Multiply, saturating at 255
+pub fn qmul8(i: u8, j: u8) -> u8 {
+	i.saturating_mul(j)
+}
+
+/// Average of `i` and `j`, rounded down
+pub fn avg8(i: u8, j: u8) -> u8 {
+	((i as u16 + j as u16) >> 1) as u8
+}
+
+/// Map `i` from 0-255 into `range_start..=range_end`
+pub fn map8(i: u8, range_start: u8, range_end: u8) -> u8 {
+	range_start.wrapping_add(scale8(i, range_end.wrapping_sub(range_start)))
+}
+
+/// Average of `i` and `j`, for signed values from -64 to 63
+pub fn avg7(i: i8, j: i8) -> i8 {
+	(i >> 1) + (j >> 1) + (i & 1)
+}
+
+/// Average of `i` and `j`, for signed values from -16384 to 16383
+pub fn avg15(i: i16, j: i16) -> i16 {
+	(i >> 1) + (j >> 1) + (i & 1)
+}
+
+/// Linear interpolation from `a` to `b`, `frac` 0 is `a` and 255 is (almost) `b`
+pub fn lerp8by8(a: u8, b: u8, frac: u8) -> u8 {
+	if b > a {
+		a + scale8(b - a, frac)
+	} else {
+		a - scale8(a - b, frac)
+	}
+}
+
+/// Linear interpolation from `a` to `b`, `frac` 0 is `a` and 65535 is (almost) `b`
+pub fn lerp16by16(a: u16, b: u16, frac: u16) -> u16 {
+	if b > a {
+		a + scale16(b - a, frac)
+	} else {
+		a - scale16(a - b, frac)
+	}
+}
+
+/// Linear interpolation between signed values from -64 to 63, `frac` 0 is `a` and 255 is (almost) `b`
+pub fn lerp7by8(a: i8, b: i8, frac: u8) -> i8 {
+	if b > a {
+		let delta = b.wrapping_sub(a) as u8;
+		a.wrapping_add(scale8(delta, frac) as i8)
+	} else {
+		let delta = a.wrapping_sub(b) as u8;
+		a.wrapping_sub(scale8(delta, frac) as i8)
+	}
+}
+
+/// Linear interpolation between signed values from -16384 to 16383, `frac` 0 is `a` and 65535 is (almost) `b`
+pub fn lerp15by16(a: i16, b: i16, frac: u16) -> i16 {
+	if b > a {
+		let delta = b.wrapping_sub(a) as u16;
+		a.wrapping_add(scale16(delta, frac) as i16)
+	} else {
+		let delta = a.wrapping_sub(b) as u16;
+		a.wrapping_sub(scale16(delta, frac) as i16)
+	}
+}
+
+/// Blend `a` and `b`, `amount_of_b` 0 is `a` and 255 is `b`
+pub fn blend8(a: u8, b: u8, amount_of_b: u8) -> u8 {
+	let partial = ((a as u32) << 8 | b as u32) + b as u32 * amount_of_b as u32;
+	((partial - a as u32 * amount_of_b as u32) >> 8) as u8
+}
+
+/// Integer square root, rounded down
+pub fn sqrt16(x: u16) -> u8 {
+	if x <= 1 {
+		return x as u8;
+	}
+
+	let mut low: u16 = 1;
+	let mut high: u16 = if x > 7904 { 255 } else { (x >> 5) + 8 };
+	loop {
+		let mid = (low + high) >> 1;
+		if mid * mid > x {
+			high = mid - 1;
+		} else {
+			if mid == 255 {
+				return 255;
+			}
+			low = mid + 1;
+		}
+		if high < low {
+			break;
+		}
+	}
+	low as u8 - 1
+}
+
+/// Sine of `theta` (256 is a full turn), from 1 to 255 with 128 as the center line
+///
+/// Approximated in 8 linear segments, accurate to about 2%.
+pub fn sin8(theta: u8) -> u8 {
+	const B_M16_INTERLEAVE: [u8; 8] = [0, 49, 49, 41, 90, 27, 117, 10];
+
+	let mut offset = theta;
+	if theta & 0x40 != 0 {
+		offset = 255 - offset;
+	}
+	offset &= 0x3F; // 0..63
+
+	let mut secoffset = offset & 0x0F; // 0..15
+	if theta & 0x40 != 0 {
+		secoffset += 1;
+	}
+
+	let section = (offset >> 4) as usize; // 0..3
+	let b = B_M16_INTERLEAVE[section * 2];
+	let m16 = B_M16_INTERLEAVE[section * 2 + 1];
+
+	let mx = ((m16 as u16 * secoffset as u16) >> 4) as u8;
+
+	let mut y = (mx + b) as i8;
+	if theta & 0x80 != 0 {
+		y = -y;
+	}
+
+	(y as i16 + 128) as u8
+}
+
+/// Cosine of `theta` (256 is a full turn), see `sin8`
+pub fn cos8(theta: u8) -> u8 {
+	sin8(theta.wrapping_add(64))
+}
+
+/// Sine of `theta` (65536 is a full turn), from -32767 to 32767
+///
+/// Approximated in 16 linear segments, accurate to about 0.7%.
+pub fn sin16(theta: u16) -> i16 {
+	const BASE: [u16; 8] = [0, 6393, 12539, 18204, 23170, 27245, 30273, 32137];
+	const SLOPE: [u8; 8] = [49, 48, 44, 38, 31, 23, 14, 4];
+
+	let mut offset = (theta & 0x3FFF) >> 3; // 0..2047
+	if theta & 0x4000 != 0 {
+		offset = 2047 - offset;
+	}
+
+	let section = (offset / 256) as usize; // 0..7
+	let b = BASE[section];
+	let m = SLOPE[section];
+
+	let secoffset8 = (offset as u8) / 2;
+
+	let mx = m as u16 * secoffset8 as u16;
+	let mut y = (mx + b) as i16;
+	if theta & 0x8000 != 0 {
+		y = -y;
+	}
+
+	y
+}
+
+/// Cosine of `theta` (65536 is a full turn), see `sin16`
+pub fn cos16(theta: u16) -> i16 {
+	sin16(theta.wrapping_add(16384))
+}
+
+/// Triangle wave, rising from 0 to 254 and falling back to 0 over one turn of `i`
+pub fn triwave8(i: u8) -> u8 {
+	let i = if i & 0x80 != 0 { 255 - i } else { i };
+	i << 1
+}
+
+/// Like `triwave8`, but with quadratic easing at the ends, close to a sine wave
+pub fn quadwave8(i: u8) -> u8 {
+	ease8_in_out_quad(triwave8(i))
+}
+
+/// Like `triwave8`, but with cubic easing at the ends, spending more time near 0 and 255
+pub fn cubicwave8(i: u8) -> u8 {
+	ease8_in_out_cubic(triwave8(i))
+}
+
+/// Quadratic ease in and out, for a fraction from 0 to 255
+pub fn ease8_in_out_quad(i: u8) -> u8 {
+	let j = if i & 0x80 != 0 { 255 - i } else { i };
+	let jj = scale8(j, j);
+	let jj2 = jj << 1;
+	if i & 0x80 != 0 {
+		255 - jj2
+	} else {
+		jj2
+	}
+}
+
+/// Quadratic ease in and out, for a fraction from 0 to 65535
+pub fn ease16_in_out_quad(i: u16) -> u16 {
+	let j = if i & 0x8000 != 0 { 65535 - i } else { i };
+	let jj = scale16(j, j);
+	let jj2 = jj << 1;
+	if i & 0x8000 != 0 {
+		65535 - jj2
+	} else {
+		jj2
+	}
+}
+
+/// Cubic ease in and out, for a fraction from 0 to 255
+pub fn ease8_in_out_cubic(i: u8) -> u8 {
+	let ii = scale8(i, i);
+	let iii = scale8(ii, i);
+
+	let r1 = 3 * ii as u16 - 2 * iii as u16;
+
+	// 256 would overflow
+	if r1 & 0x100 != 0 {
+		255
+	} else {
+		r1 as u8
+	}
+}
+
+/// A sawtooth wave, rising from 0 to 65535 `bpm88` times per minute
+///
+/// `bpm88` is in Q8.8 fixed point, so 120 BPM is `120 << 8`.
+/// `ms` is the time in milliseconds, since whenever the beat should start.
+pub fn beat88(bpm88: u16, ms: u32) -> u16 {
+	// 280 is 65536 / 60000 * 256, only the low 32 bits matter
+	(ms.wrapping_mul(bpm88 as u32).wrapping_mul(280) >> 16) as u16
+}
+
+/// A sawtooth wave, rising from 0 to 65535 `bpm` times per minute
+///
+/// `bpm` below 256 is taken as a whole number, above as Q8.8 like `beat88`.
+pub fn beat16(bpm: u16, ms: u32) -> u16 {
+	let bpm = if bpm < 256 { bpm << 8 } else { bpm };
+	beat88(bpm, ms)
+}
+
+/// A sawtooth wave, rising from 0 to 255 `bpm` times per minute
+pub fn beat8(bpm: u16, ms: u32) -> u8 {
+	(beat16(bpm, ms) >> 8) as u8
+}
+
+/// A sine wave between `lowest` and `highest`, oscillating `bpm` times per minute
+///
+/// `phase_offset` shifts the wave, 256 being a full period.
+pub fn beatsin8(bpm: u16, lowest: u8, highest: u8, ms: u32, phase_offset: u8) -> u8 {
+	let beat = beat8(bpm, ms);
+	let beatsin = sin8(beat.wrapping_add(phase_offset));
+	lowest.wrapping_add(scale8(beatsin, highest.wrapping_sub(lowest)))
+}
+
+/// A sine wave between `lowest` and `highest`, oscillating `bpm` times per minute
+///
+/// `phase_offset` shifts the wave, 65536 being a full period.
+pub fn beatsin16(bpm: u16, lowest: u16, highest: u16, ms: u32, phase_offset: u16) -> u16 {
+	let beat = beat16(bpm, ms);
+	let beatsin = (sin16(beat.wrapping_add(phase_offset)) as i32 + 32768) as u16;
+	lowest.wrapping_add(scale16(beatsin, highest.wrapping_sub(lowest)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sin8_matches_fastled() {
+		let expected = [128, 218, 255, 218, 128, 38, 1, 38];
+		for (i, &value) in expected.iter().enumerate() {
+			assert_eq!(sin8(i as u8 * 32), value);
+		}
+		assert_eq!(cos8(0), 255);
+	}
+
+	#[test]
+	fn sin16_matches_fastled() {
+		assert_eq!(sin16(0), 0);
+		assert_eq!(sin16(8192), 23170);
+		assert_eq!(sin16(16384), 32645);
+		assert_eq!(sin16(32768), 0);
+		assert_eq!(sin16(49152), -32645);
+		assert_eq!(cos16(0), 32645);
+	}
+
+	#[test]
+	fn sqrt16_rounds_down() {
+		assert_eq!(sqrt16(0), 0);
+		assert_eq!(sqrt16(1), 1);
+		assert_eq!(sqrt16(100), 10);
+		assert_eq!(sqrt16(255), 15);
+		assert_eq!(sqrt16(256), 16);
+		assert_eq!(sqrt16(1000), 31);
+		assert_eq!(sqrt16(65535), 255);
+	}
+
+	#[test]
+	fn blend8_matches_fastled() {
+		assert_eq!(blend8(0, 255, 0), 0);
+		assert_eq!(blend8(0, 255, 128), 128);
+		assert_eq!(blend8(0, 255, 255), 255);
+		assert_eq!(blend8(255, 0, 0), 255);
+		assert_eq!(blend8(100, 200, 64), 125);
+	}
+
+	#[test]
+	fn scale8_and_saturation() {
+		assert_eq!(scale8(255, 128), 128);
+		assert_eq!(scale8(1, 254), 0);
+		assert_eq!(scale8_video(1, 1), 1);
+		assert_eq!(scale8_video(0, 255), 0);
+		assert_eq!(qadd8(200, 100), 255);
+		assert_eq!(qsub8(100, 200), 0);
+	}
+
+	#[test]
+	fn reversed_ranges_wrap_like_fastled() {
+		assert_eq!(map8(0, 200, 100), 200);
+		assert_eq!(map8(255, 200, 100), 100);
+		assert_eq!(beatsin8(60, 200, 100, 0, 64), 100);
+		assert_eq!(beatsin8(60, 200, 100, 0, 0), 22);
+		assert_eq!(beatsin16(60, 60_000, 1_000, 0, 16_384), 988);
+	}
+}
@@ -1,3 +1,17 @@
+pub mod cellular;
+pub mod fractal;
+pub mod grid;
+
+use crate::math8::{
+	avg15,
+	avg7,
+	ease16_in_out_quad,
+	ease8_in_out_quad,
+	lerp15by16,
+	lerp7by8,
+	qadd8,
+};
+use libm::floorf;
 
 const GRAD3: [[i32; 3]; 12] = [
 	[1, 1, 0],
@@ -63,32 +77,110 @@ fn fastfloor(x: f32) -> i32 {
 }
 
 fn dot(g: [i32; 3], x: f32, y: f32, z: f32) -> f32 {
-	return g[0] as f32 * x + g[1] as f32 * y + g[2] as f32 * z;
+	g[0] as f32 * x + g[1] as f32 * y + g[2] as f32 * z
 }
 
-// 3D simplex noise
-pub fn simplex(xin: f32, yin: f32, zin: f32) -> f32 {
-	// Noise contributions from the four corners
-	let n0;
-	let n1;
-	let n2;
-	let n3;
-
-	// Skew the input space to determine which simplex cell we're in
-	let f3 = 1.0 / 3.0;
-	let s = (xin + yin + zin) * f3; // Very nice and simple skew factor for 3D
-	let i = fastfloor(xin + s) as f32;
-	let j = fastfloor(yin + s) as f32;
-	let k = fastfloor(zin + s) as f32;
-
-	let g3 = 1.0 / 6.0; // Very nice and simple unskew factor, too
-	let t = (i + j + k) * g3;
-	let x0_ = i - t; // Unskew the cell origin back to (x,y,z) space
-	let y0_ = j - t;
-	let z0_ = k - t;
-	let x0 = xin - x0_; // The x,y,z distances from the cell origin
-	let y0 = yin - y0_;
-	let z0 = zin - z0_;
+const GRAD4: [[i32; 4]; 32] = [
+	[0, 1, 1, 1],
+	[0, 1, 1, -1],
+	[0, 1, -1, 1],
+	[0, 1, -1, -1],
+	[0, -1, 1, 1],
+	[0, -1, 1, -1],
+	[0, -1, -1, 1],
+	[0, -1, -1, -1],
+	[1, 0, 1, 1],
+	[1, 0, 1, -1],
+	[1, 0, -1, 1],
+	[1, 0, -1, -1],
+	[-1, 0, 1, 1],
+	[-1, 0, 1, -1],
+	[-1, 0, -1, 1],
+	[-1, 0, -1, -1],
+	[1, 1, 0, 1],
+	[1, 1, 0, -1],
+	[1, -1, 0, 1],
+	[1, -1, 0, -1],
+	[-1, 1, 0, 1],
+	[-1, 1, 0, -1],
+	[-1, -1, 0, 1],
+	[-1, -1, 0, -1],
+	[1, 1, 1, 0],
+	[1, 1, -1, 0],
+	[1, -1, 1, 0],
+	[1, -1, -1, 0],
+	[-1, 1, 1, 0],
+	[-1, 1, -1, 0],
+	[-1, -1, 1, 0],
+	[-1, -1, -1, 0],
+];
+
+/// Index into the permutation table of a lattice coordinate
+fn wrap(i: f32) -> usize {
+	(i as i32 & 255) as usize
+}
+
+fn fade(t: f32) -> f32 {
+	t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+	a + t * (b - a)
+}
+
+fn grad(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+	// Convert the low 4 bits of the hash into 12 gradient directions
+	let h = hash & 15;
+	let u = if h < 8 { x } else { y };
+	let v = if h < 4 {
+		y
+	} else if h == 12 || h == 14 {
+		x
+	} else {
+		z
+	};
+	(if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+fn grad8(hash: u8, x: i8, y: i8, z: i8) -> i8 {
+	let hash = hash & 15;
+	let u = if hash < 8 { x } else { y };
+	let v = if hash < 4 {
+		y
+	} else if hash == 12 || hash == 14 {
+		x
+	} else {
+		z
+	};
+	let u = if hash & 1 != 0 { u.wrapping_neg() } else { u };
+	let v = if hash & 2 != 0 { v.wrapping_neg() } else { v };
+	avg7(u, v)
+}
+
+fn grad16(hash: u8, x: i16, y: i16, z: i16) -> i16 {
+	let hash = hash & 15;
+	let u = if hash < 8 { x } else { y };
+	let v = if hash < 4 {
+		y
+	} else if hash == 12 || hash == 14 {
+		x
+	} else {
+		z
+	};
+	let u = if hash & 1 != 0 { u.wrapping_neg() } else { u };
+	let v = if hash & 2 != 0 { v.wrapping_neg() } else { v };
+	avg15(u, v)
+}
+
+/// The sum of the contributions of the four corners of a 3D simplex,
+/// `gradient` gives the gradient index of the corner at an offset in (i,j,k) coords
+fn simplex3_corners<G: Fn(usize, usize, usize) -> usize>(
+	x0: f32,
+	y0: f32,
+	z0: f32,
+	gradient: G,
+) -> f32 {
+	let g3 = 1.0 / 6.0;
 
 	// For the 3D case, the simplex shape is a slightly irregular tetrahedron.
 	// Determine which simplex we are in.
@@ -172,46 +264,566 @@ pub fn simplex(xin: f32, yin: f32, zin: f32) -> f32 {
 	let y3 = y0 - 1.0 + 3.0 * g3;
 	let z3 = z0 - 1.0 + 3.0 * g3;
 
-	// Work out the hashed gradient indices of the four simplex corners
-	let ii = i as usize & 255;
-	let jj = j as usize & 255;
-	let kk = k as usize & 255;
-	let gi0 = PERM[ii + PERM[jj + PERM[kk]]] % 12;
-	let gi1 = PERM[ii + i1 + PERM[jj + j1 + PERM[kk + k1]]] % 12;
-	let gi2 = PERM[ii + i2 + PERM[jj + j2 + PERM[kk + k2]]] % 12;
-	let gi3 = PERM[ii + 1 + PERM[jj + 1 + PERM[kk + 1]]] % 12;
+	let gi0 = gradient(0, 0, 0);
+	let gi1 = gradient(i1, j1, k1);
+	let gi2 = gradient(i2, j2, k2);
+	let gi3 = gradient(1, 1, 1);
 
-	// Calculate the contribution from the four corners
+	// Calculate the noise contribution from the four corners
 	let mut t0 = 0.5 - x0 * x0 - y0 * y0 - z0 * z0;
-	if t0 < 0.0 {
-		n0 = 0.0;
+	let n0 = if t0 < 0.0 {
+		0.0
 	} else {
 		t0 *= t0;
-		n0 = t0 * t0 * dot(GRAD3[gi0], x0, y0, z0);
-	}
+		t0 * t0 * dot(GRAD3[gi0], x0, y0, z0)
+	};
 	let mut t1 = 0.5 - x1 * x1 - y1 * y1 - z1 * z1;
-	if t1 < 0.0 {
-		n1 = 0.0;
+	let n1 = if t1 < 0.0 {
+		0.0
 	} else {
 		t1 *= t1;
-		n1 = t1 * t1 * dot(GRAD3[gi1], x1, y1, z1);
-	}
+		t1 * t1 * dot(GRAD3[gi1], x1, y1, z1)
+	};
 	let mut t2 = 0.5 - x2 * x2 - y2 * y2 - z2 * z2;
-	if t2 < 0.0 {
-		n2 = 0.0;
+	let n2 = if t2 < 0.0 {
+		0.0
 	} else {
 		t2 *= t2;
-		n2 = t2 * t2 * dot(GRAD3[gi2], x2, y2, z2);
-	}
+		t2 * t2 * dot(GRAD3[gi2], x2, y2, z2)
+	};
 	let mut t3 = 0.5 - x3 * x3 - y3 * y3 - z3 * z3;
-	if t3 < 0.0 {
-		n3 = 0.0;
+	let n3 = if t3 < 0.0 {
+		0.0
 	} else {
 		t3 *= t3;
-		n3 = t3 * t3 * dot(GRAD3[gi3], x3, y3, z3);
-	}
+		t3 * t3 * dot(GRAD3[gi3], x3, y3, z3)
+	};
 
 	// Add contributions from each corner to get the final noise value.
 	// The result is scaled to stay just inside [-1,1]
-	return 32.0 * (n0 + n1 + n2 + n3);
+	32.0 * (n0 + n1 + n2 + n3)
+}
+
+/// A noise generator with its own permutation table
+///
+/// Generators built from different seeds give completely different patterns,
+/// so several independent noise fields can be used at the same time.
+#[derive(Clone)]
+pub struct Noise {
+	perm: [u8; 512],
+}
+
+// Ken Perlin's reference permutation, used by the free functions
+static REFERENCE: Noise = Noise::reference();
+
+impl Noise {
+	/// Create a generator with a permutation table shuffled from `seed`
+	pub fn new(seed: u32) -> Self {
+		let mut perm = [0; 512];
+		for (i, p) in perm.iter_mut().take(256).enumerate() {
+			*p = i as u8;
+		}
+
+		// xorshift32, which gets stuck on zero
+		let mut rng = if seed == 0 { 0x2545_f491 } else { seed };
+		for i in (1..256).rev() {
+			rng ^= rng << 13;
+			rng ^= rng >> 17;
+			rng ^= rng << 5;
+			perm.swap(i, rng as usize % (i + 1));
+		}
+
+		for i in 0..256 {
+			perm[i + 256] = perm[i];
+		}
+		Noise { perm }
+	}
+
+	/// Create a generator with Ken Perlin's reference permutation,
+	/// the same as the free functions in this module
+	pub const fn reference() -> Self {
+		let mut perm = [0; 512];
+		let mut i = 0;
+		while i < 512 {
+			perm[i] = PERM[i] as u8;
+			i += 1;
+		}
+		Noise { perm }
+	}
+
+	fn p(&self, i: usize) -> usize {
+		self.perm[i] as usize
+	}
+
+	/// 3D simplex noise
+	pub fn simplex(&self, xin: f32, yin: f32, zin: f32) -> f32 {
+		// Skew the input space to determine which simplex cell we're in
+		let f3 = 1.0 / 3.0;
+		let s = (xin + yin + zin) * f3; // Very nice and simple skew factor for 3D
+		let i = fastfloor(xin + s) as f32;
+		let j = fastfloor(yin + s) as f32;
+		let k = fastfloor(zin + s) as f32;
+
+		let g3 = 1.0 / 6.0; // Very nice and simple unskew factor, too
+		let t = (i + j + k) * g3;
+		let x0_ = i - t; // Unskew the cell origin back to (x,y,z) space
+		let y0_ = j - t;
+		let z0_ = k - t;
+		let x0 = xin - x0_; // The x,y,z distances from the cell origin
+		let y0 = yin - y0_;
+		let z0 = zin - z0_;
+
+		// Work out the hashed gradient indices of the simplex corners
+		let ii = wrap(i);
+		let jj = wrap(j);
+		let kk = wrap(k);
+		simplex3_corners(x0, y0, z0, |i1, j1, k1| {
+			self.p(ii + i1 + self.p(jj + j1 + self.p(kk + k1))) % 12
+		})
+	}
+
+	/// 2D simplex noise, from -1 to 1
+	pub fn simplex2(&self, xin: f32, yin: f32) -> f32 {
+		let f2 = 0.366_025_4; // (sqrt(3) - 1) / 2
+		let g2 = 0.211_324_87; // (3 - sqrt(3)) / 6
+
+		// Skew the input space to determine which simplex cell we're in
+		let s = (xin + yin) * f2;
+		let i = floorf(xin + s);
+		let j = floorf(yin + s);
+		let t = (i + j) * g2;
+		let x0 = xin - (i - t); // The x,y distances from the cell origin
+		let y0 = yin - (j - t);
+
+		// In 2D the simplex is a triangle, the upper or the lower one of the cell
+		let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+		let x1 = x0 - i1 as f32 + g2;
+		let y1 = y0 - j1 as f32 + g2;
+		let x2 = x0 - 1.0 + 2.0 * g2;
+		let y2 = y0 - 1.0 + 2.0 * g2;
+
+		let ii = wrap(i);
+		let jj = wrap(j);
+		let corners = [
+			(self.p(ii + self.p(jj)) % 12, x0, y0),
+			(self.p(ii + i1 + self.p(jj + j1)) % 12, x1, y1),
+			(self.p(ii + 1 + self.p(jj + 1)) % 12, x2, y2),
+		];
+
+		let mut n = 0.0;
+		for &(gi, x, y) in corners.iter() {
+			let t = 0.5 - x * x - y * y;
+			if t > 0.0 {
+				let t = t * t;
+				n += t * t * dot(GRAD3[gi], x, y, 0.0);
+			}
+		}
+
+		// Scaled to stay just inside [-1,1]
+		70.0 * n
+	}
+
+	/// 4D simplex noise, from -1 to 1
+	///
+	/// Moving in a circle through two of the dimensions loops seamlessly.
+	pub fn simplex4(&self, x: f32, y: f32, z: f32, w: f32) -> f32 {
+		let f4 = 0.309_017; // (sqrt(5) - 1) / 4
+		let g4 = 0.138_196_6; // (5 - sqrt(5)) / 20
+
+		// Skew the (x,y,z,w) space to determine which cell of 24 simplices we're in
+		let s = (x + y + z + w) * f4;
+		let i = floorf(x + s);
+		let j = floorf(y + s);
+		let k = floorf(z + s);
+		let l = floorf(w + s);
+		let t = (i + j + k + l) * g4;
+		let p0 = [x - (i - t), y - (j - t), z - (k - t), w - (l - t)];
+
+		// Rank the coordinates by magnitude, which determines the order of
+		// the corners of the simplex
+		let mut rank = [0; 4];
+		for a in 0..4 {
+			for b in a + 1..4 {
+				if p0[a] > p0[b] {
+					rank[a] += 1;
+				} else {
+					rank[b] += 1;
+				}
+			}
+		}
+
+		let ii = wrap(i);
+		let jj = wrap(j);
+		let kk = wrap(k);
+		let ll = wrap(l);
+
+		let mut n = 0.0;
+		for corner in 0..5 {
+			// Offsets of this corner in (i,j,k,l) coords
+			let offset = |r: usize| if rank[r] >= 4 - corner { 1 } else { 0 };
+			let (i1, j1, k1, l1) = (offset(0), offset(1), offset(2), offset(3));
+			let unskew = corner as f32 * g4;
+			let p = [
+				p0[0] - i1 as f32 + unskew,
+				p0[1] - j1 as f32 + unskew,
+				p0[2] - k1 as f32 + unskew,
+				p0[3] - l1 as f32 + unskew,
+			];
+
+			let t = 0.6 - p[0] * p[0] - p[1] * p[1] - p[2] * p[2] - p[3] * p[3];
+			if t > 0.0 {
+				let gi = self.p(ii + i1 + self.p(jj + j1 + self.p(kk + k1 + self.p(ll + l1)))) % 32;
+				let g = GRAD4[gi];
+				let dot = g[0] as f32 * p[0]
+					+ g[1] as f32 * p[1]
+					+ g[2] as f32 * p[2]
+					+ g[3] as f32 * p[3];
+				let t = t * t;
+				n += t * t * dot;
+			}
+		}
+
+		// Scaled to stay just inside [-1,1]
+		27.0 * n
+	}
+
+	/// 3D classic (improved) Perlin noise, from about -1 to 1
+	pub fn perlin(&self, x: f32, y: f32, z: f32) -> f32 {
+		// Find the unit cube that contains the point
+		let xi = wrap(floorf(x));
+		let yi = wrap(floorf(y));
+		let zi = wrap(floorf(z));
+
+		// Find relative x,y,z of the point in the cube
+		let x = x - floorf(x);
+		let y = y - floorf(y);
+		let z = z - floorf(z);
+
+		// Compute fade curves for each of x,y,z
+		let u = fade(x);
+		let v = fade(y);
+		let w = fade(z);
+
+		// Hash coordinates of the 8 cube corners
+		let a = self.p(xi) + yi;
+		let aa = self.p(a) + zi;
+		let ab = self.p(a + 1) + zi;
+		let b = self.p(xi + 1) + yi;
+		let ba = self.p(b) + zi;
+		let bb = self.p(b + 1) + zi;
+
+		// And add blended results from the 8 corners of the cube
+		lerp(
+			w,
+			lerp(
+				v,
+				lerp(
+					u,
+					grad(self.p(aa), x, y, z),
+					grad(self.p(ba), x - 1.0, y, z),
+				),
+				lerp(
+					u,
+					grad(self.p(ab), x, y - 1.0, z),
+					grad(self.p(bb), x - 1.0, y - 1.0, z),
+				),
+			),
+			lerp(
+				v,
+				lerp(
+					u,
+					grad(self.p(aa + 1), x, y, z - 1.0),
+					grad(self.p(ba + 1), x - 1.0, y, z - 1.0),
+				),
+				lerp(
+					u,
+					grad(self.p(ab + 1), x, y - 1.0, z - 1.0),
+					grad(self.p(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+				),
+			),
+		)
+	}
+
+	/// 3D value noise, from -1 to 1
+	///
+	/// Random values on the integer lattice, smoothly interpolated. Cheaper than
+	/// gradient noise, but blockier.
+	pub fn value(&self, x: f32, y: f32, z: f32) -> f32 {
+		let xi = wrap(floorf(x));
+		let yi = wrap(floorf(y));
+		let zi = wrap(floorf(z));
+
+		let u = fade(x - floorf(x));
+		let v = fade(y - floorf(y));
+		let w = fade(z - floorf(z));
+
+		let lattice = |dx: usize, dy: usize, dz: usize| {
+			self.p(self.p(self.p(xi + dx) + yi + dy) + zi + dz) as f32 / 127.5 - 1.0
+		};
+
+		lerp(
+			w,
+			lerp(
+				v,
+				lerp(u, lattice(0, 0, 0), lattice(1, 0, 0)),
+				lerp(u, lattice(0, 1, 0), lattice(1, 1, 0)),
+			),
+			lerp(
+				v,
+				lerp(u, lattice(0, 0, 1), lattice(1, 0, 1)),
+				lerp(u, lattice(0, 1, 1), lattice(1, 1, 1)),
+			),
+		)
+	}
+
+	/// 3D gradient noise in 8 bit fixed point, from about -64 to 64, from FastLED
+	///
+	/// The coordinates are in 8.8 fixed point, so one unit of the noise is 256.
+	/// Doesn't need an FPU.
+	pub fn inoise8_raw(&self, x: u16, y: u16, z: u16) -> i8 {
+		// Find the unit cube containing the point
+		let xi = (x >> 8) as u8;
+		let yi = (y >> 8) as u8;
+		let zi = (z >> 8) as u8;
+
+		// Hash cube corner coordinates
+		let a = (self.p(xi as usize) as u8).wrapping_add(yi);
+		let aa = (self.p(a as usize) as u8).wrapping_add(zi) as usize;
+		let ab = (self.p(a as usize + 1) as u8).wrapping_add(zi) as usize;
+		let b = (self.p(xi as usize + 1) as u8).wrapping_add(yi);
+		let ba = (self.p(b as usize) as u8).wrapping_add(zi) as usize;
+		let bb = (self.p(b as usize + 1) as u8).wrapping_add(zi) as usize;
+
+		// Get the relative position of the point in the cube,
+		// and a signed version of it for the gradients
+		let u = ease8_in_out_quad(x as u8);
+		let v = ease8_in_out_quad(y as u8);
+		let w = ease8_in_out_quad(z as u8);
+		let xx = ((x as u8) >> 1) as i8;
+		let yy = ((y as u8) >> 1) as i8;
+		let zz = ((z as u8) >> 1) as i8;
+		let n = -128;
+
+		let g = |hash: usize, x: i8, y: i8, z: i8| grad8(self.p(hash) as u8, x, y, z);
+		let x1 = lerp7by8(g(aa, xx, yy, zz), g(ba, xx + n, yy, zz), u);
+		let x2 = lerp7by8(g(ab, xx, yy + n, zz), g(bb, xx + n, yy + n, zz), u);
+		let x3 = lerp7by8(g(aa + 1, xx, yy, zz + n), g(ba + 1, xx + n, yy, zz + n), u);
+		let x4 = lerp7by8(
+			g(ab + 1, xx, yy + n, zz + n),
+			g(bb + 1, xx + n, yy + n, zz + n),
+			u,
+		);
+
+		let y1 = lerp7by8(x1, x2, v);
+		let y2 = lerp7by8(x3, x4, v);
+
+		lerp7by8(y1, y2, w)
+	}
+
+	/// 3D gradient noise from 0 to 255, see `inoise8_raw`
+	pub fn inoise8(&self, x: u16, y: u16, z: u16) -> u8 {
+		let n = (self.inoise8_raw(x, y, z) as i16 + 64).max(0) as u8;
+		qadd8(n, n)
+	}
+
+	/// 3D gradient noise in 16 bit fixed point, from about -18000 to 18000, from FastLED
+	///
+	/// The coordinates are in 16.16 fixed point, so one unit of the noise is 65536.
+	/// Doesn't need an FPU.
+	pub fn inoise16_raw(&self, x: u32, y: u32, z: u32) -> i16 {
+		// Find the unit cube containing the point
+		let xi = (x >> 16) as u8;
+		let yi = (y >> 16) as u8;
+		let zi = (z >> 16) as u8;
+
+		// Hash cube corner coordinates
+		let a = (self.p(xi as usize) as u8).wrapping_add(yi);
+		let aa = (self.p(a as usize) as u8).wrapping_add(zi) as usize;
+		let ab = (self.p(a as usize + 1) as u8).wrapping_add(zi) as usize;
+		let b = (self.p(xi as usize + 1) as u8).wrapping_add(yi);
+		let ba = (self.p(b as usize) as u8).wrapping_add(zi) as usize;
+		let bb = (self.p(b as usize + 1) as u8).wrapping_add(zi) as usize;
+
+		// Get the relative position of the point in the cube,
+		// and a signed version of it for the gradients
+		let u = ease16_in_out_quad(x as u16);
+		let v = ease16_in_out_quad(y as u16);
+		let w = ease16_in_out_quad(z as u16);
+		let xx = ((x as u16) >> 1) as i16;
+		let yy = ((y as u16) >> 1) as i16;
+		let zz = ((z as u16) >> 1) as i16;
+		let n = -32768;
+
+		let g = |hash: usize, x: i16, y: i16, z: i16| grad16(self.p(hash) as u8, x, y, z);
+		let x1 = lerp15by16(g(aa, xx, yy, zz), g(ba, xx + n, yy, zz), u);
+		let x2 = lerp15by16(g(ab, xx, yy + n, zz), g(bb, xx + n, yy + n, zz), u);
+		let x3 = lerp15by16(g(aa + 1, xx, yy, zz + n), g(ba + 1, xx + n, yy, zz + n), u);
+		let x4 = lerp15by16(
+			g(ab + 1, xx, yy + n, zz + n),
+			g(bb + 1, xx + n, yy + n, zz + n),
+			u,
+		);
+
+		let y1 = lerp15by16(x1, x2, v);
+		let y2 = lerp15by16(x3, x4, v);
+
+		lerp15by16(y1, y2, w)
+	}
+
+	/// 3D gradient noise from 0 to 65535, see `inoise16_raw`
+	pub fn inoise16(&self, x: u32, y: u32, z: u32) -> u16 {
+		let n = (self.inoise16_raw(x, y, z) as i32 + 19052).max(0) as u32;
+		((n * 440) >> 8).min(65535) as u16
+	}
+}
+
+/// `Noise::simplex` with the reference permutation
+pub fn simplex(xin: f32, yin: f32, zin: f32) -> f32 {
+	REFERENCE.simplex(xin, yin, zin)
+}
+
+/// `Noise::simplex2` with the reference permutation
+pub fn simplex2(xin: f32, yin: f32) -> f32 {
+	REFERENCE.simplex2(xin, yin)
+}
+
+/// `Noise::simplex4` with the reference permutation
+pub fn simplex4(x: f32, y: f32, z: f32, w: f32) -> f32 {
+	REFERENCE.simplex4(x, y, z, w)
+}
+
+/// `Noise::perlin` with the reference permutation
+pub fn perlin(x: f32, y: f32, z: f32) -> f32 {
+	REFERENCE.perlin(x, y, z)
+}
+
+/// `Noise::value` with the reference permutation
+pub fn value(x: f32, y: f32, z: f32) -> f32 {
+	REFERENCE.value(x, y, z)
+}
+
+/// `Noise::inoise8_raw` with the reference permutation
+pub fn inoise8_raw(x: u16, y: u16, z: u16) -> i8 {
+	REFERENCE.inoise8_raw(x, y, z)
+}
+
+/// `Noise::inoise8` with the reference permutation
+pub fn inoise8(x: u16, y: u16, z: u16) -> u8 {
+	REFERENCE.inoise8(x, y, z)
+}
+
+/// `Noise::inoise16_raw` with the reference permutation
+pub fn inoise16_raw(x: u32, y: u32, z: u32) -> i16 {
+	REFERENCE.inoise16_raw(x, y, z)
+}
+
+/// `Noise::inoise16` with the reference permutation
+pub fn inoise16(x: u32, y: u32, z: u32) -> u16 {
+	REFERENCE.inoise16(x, y, z)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// a spread of points, including negative and non-integer coordinates
+	fn points() -> impl Iterator<Item = (f32, f32, f32)> {
+		(0..500).map(|i| {
+			let i = i as f32;
+			(i * 0.37 - 90.0, i * 0.71 - 170.0, i * 0.13)
+		})
+	}
+
+	#[test]
+	fn simplex_matches_known_values() {
+		// from the implementation this module was ported with
+		let known = [
+			(0.0, 0.0, 0.0, 0.0),
+			(0.5, 0.25, 0.125, 0.083_960_95),
+			(1.7, 3.2, 0.9, -0.058_138_274),
+			(12.3, 4.56, 7.89, 0.061_531_648),
+			(100.1, 200.2, 3.3, 0.204_805_02),
+			(0.3, 0.6, 0.9, -0.009_483_812),
+			(0.7, 0.2, 2.5, 0.036_420_1),
+		];
+		for &(x, y, z, value) in &known {
+			let n = simplex(x, y, z);
+			assert!((n - value).abs() < 1e-6, "{} at {} {} {}", n, x, y, z);
+		}
+	}
+
+	#[test]
+	fn float_noise_stays_in_range() {
+		for (x, y, z) in points() {
+			for n in [
+				simplex(x, y, z),
+				simplex2(x, y),
+				simplex4(x, y, z, x - y),
+				perlin(x, y, z),
+				value(x, y, z),
+			] {
+				assert!((-1.0..=1.0).contains(&n), "{} at {} {} {}", n, x, y, z);
+			}
+		}
+	}
+
+	#[test]
+	fn same_seed_same_noise() {
+		let (a, b, other) = (Noise::new(42), Noise::new(42), Noise::new(43));
+		let mut differs = false;
+		for (x, y, z) in points() {
+			assert_eq!(a.simplex(x, y, z), b.simplex(x, y, z));
+			assert_eq!(a.perlin(x, y, z), b.perlin(x, y, z));
+			assert_eq!(a.value(x, y, z), b.value(x, y, z));
+			differs |= a.simplex(x, y, z) != other.simplex(x, y, z);
+		}
+		assert!(differs);
+	}
+
+	#[test]
+	fn zero_seed_is_usable() {
+		let noise = Noise::new(0);
+		assert!(points().any(|(x, y, z)| noise.simplex(x, y, z) != 0.0));
+	}
+
+	#[test]
+	fn free_functions_use_the_reference_permutation() {
+		let reference = Noise::reference();
+		for (x, y, z) in points() {
+			assert_eq!(simplex(x, y, z), reference.simplex(x, y, z));
+			assert_eq!(perlin(x, y, z), reference.perlin(x, y, z));
+		}
+	}
+
+	#[test]
+	fn fixed_point_noise_covers_the_range() {
+		let noise = Noise::new(7);
+		let (mut min8, mut max8) = (u8::MAX, u8::MIN);
+		let (mut min16, mut max16) = (u16::MAX, u16::MIN);
+		for i in 0..20_000u32 {
+			let (x, y, z) = (i.wrapping_mul(7919), i.wrapping_mul(104_729), i * 977);
+			let n = noise.inoise8(x as u16, y as u16, z as u16);
+			min8 = min8.min(n);
+			max8 = max8.max(n);
+			let n = noise.inoise16(x, y, z);
+			min16 = min16.min(n);
+			max16 = max16.max(n);
+		}
+		assert!(min8 < 64 && max8 > 192, "{} {}", min8, max8);
+		assert!(min16 < 16384 && max16 > 49152, "{} {}", min16, max16);
+	}
+
+	#[test]
+	fn fixed_point_free_functions_use_the_reference_permutation() {
+		let reference = Noise::reference();
+		for i in 0..1000u32 {
+			let (x, y, z) = (i * 97, i * 211, i * 13);
+			assert_eq!(
+				inoise8(x as u16, y as u16, z as u16),
+				reference.inoise8(x as u16, y as u16, z as u16)
+			);
+			assert_eq!(inoise16(x, y, z), reference.inoise16(x, y, z));
+		}
+	}
 }
@@ -0,0 +1,209 @@
+//! Worley (cellular) noise
+//!
+//! Every unit cell of the lattice has one random feature point. The noise is
+//! the distance to the nearest (F1) and second nearest (F2) of these points,
+//! which gives crystal and stained-glass like patterns. The id of the nearest
+//! cell can be used to colour every cell differently.
+
+use super::{wrap, Noise, REFERENCE};
+use libm::{floorf, sqrtf};
+
+/// How the distance to the feature points is measured
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Distance {
+	/// Straight line distance, round cells
+	Euclidean,
+	/// Sum of the distances along every axis, diamond shaped cells
+	Manhattan,
+	/// Largest distance along any axis, square cells
+	Chebyshev,
+}
+
+impl Distance {
+	fn measure(self, d: [f32; 3]) -> f32 {
+		match self {
+			// the square root is taken at the end
+			Distance::Euclidean => d[0] * d[0] + d[1] * d[1] + d[2] * d[2],
+			Distance::Manhattan => d[0].abs() + d[1].abs() + d[2].abs(),
+			Distance::Chebyshev => d[0].abs().max(d[1].abs()).max(d[2].abs()),
+		}
+	}
+}
+
+/// The result of sampling cellular noise at one point
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cell {
+	/// Distance to the nearest feature point
+	pub f1: f32,
+	/// Distance to the second nearest feature point
+	pub f2: f32,
+	/// Random id of the cell of the nearest feature point, 24 bits
+	pub id: u32,
+}
+
+impl Cell {
+	/// Zero on the borders between cells, for outlines and cracks
+	pub fn edge(&self) -> f32 {
+		self.f2 - self.f1
+	}
+}
+
+impl Noise {
+	/// Hash of a cell, one byte each for the offset of the feature point
+	/// along every axis
+	fn cell_hash(&self, xi: usize, yi: usize, zi: usize) -> [u8; 3] {
+		let hash = |salt: usize| self.p(self.p(self.p(xi + salt) + yi) + zi) as u8;
+		[hash(0), hash(85), hash(170)]
+	}
+
+	/// Search `reach` cells in every direction, and `layers` cells along z
+	fn search(
+		&self,
+		(x, y, z): (f32, f32, f32),
+		reach: i32,
+		layers: i32,
+		distance: Distance,
+	) -> Cell {
+		let cx = floorf(x);
+		let cy = floorf(y);
+		let cz = floorf(z);
+
+		let mut f1 = f32::MAX;
+		let mut f2 = f32::MAX;
+		let mut id = 0;
+
+		for dz in -layers..=layers {
+			for dy in -reach..=reach {
+				for dx in -reach..=reach {
+					let (nx, ny, nz) = (cx + dx as f32, cy + dy as f32, cz + dz as f32);
+					let hash = self.cell_hash(wrap(nx), wrap(ny), wrap(nz));
+					let offset = |i: usize| hash[i] as f32 / 256.0;
+					let d = distance.measure([
+						nx + offset(0) - x,
+						ny + offset(1) - y,
+						if layers == 0 { 0.0 } else { nz + offset(2) - z },
+					]);
+					if d < f1 {
+						f2 = f1;
+						f1 = d;
+						id = hash[0] as u32 | (hash[1] as u32) << 8 | (hash[2] as u32) << 16;
+					} else if d < f2 {
+						f2 = d;
+					}
+				}
+			}
+		}
+
+		if distance == Distance::Euclidean {
+			f1 = sqrtf(f1);
+			f2 = sqrtf(f2);
+		}
+		Cell { f1, f2, id }
+	}
+
+	fn cellular(&self, x: f32, y: f32, z: f32, layers: i32, distance: Distance) -> Cell {
+		// F1 is always in one of the neighbouring cells, but F2 can be two cells
+		// away, near the corners of a cell
+		self.search((x, y, z), 2, layers * 2, distance)
+	}
+
+	/// 2D cellular noise, F1 is mostly below 1, but can reach about 2 with `Manhattan`
+	pub fn cellular2(&self, x: f32, y: f32, distance: Distance) -> Cell {
+		self.cellular(x, y, 0.0, 0, distance)
+	}
+
+	/// 3D cellular noise, F1 is mostly below 1, but can reach about 2 with `Manhattan`
+	pub fn cellular3(&self, x: f32, y: f32, z: f32, distance: Distance) -> Cell {
+		self.cellular(x, y, z, 1, distance)
+	}
+}
+
+/// `Noise::cellular2` with the reference permutation
+pub fn cellular2(x: f32, y: f32, distance: Distance) -> Cell {
+	REFERENCE.cellular2(x, y, distance)
+}
+
+/// `Noise::cellular3` with the reference permutation
+pub fn cellular3(x: f32, y: f32, z: f32, distance: Distance) -> Cell {
+	REFERENCE.cellular3(x, y, z, distance)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DISTANCES: [Distance; 3] = [
+		Distance::Euclidean,
+		Distance::Manhattan,
+		Distance::Chebyshev,
+	];
+
+	fn points() -> impl Iterator<Item = (f32, f32, f32)> {
+		(0..300).map(|i| {
+			let i = i as f32;
+			(i * 0.37 - 50.0, i * 0.71 - 90.0, i * 0.13)
+		})
+	}
+
+	#[test]
+	fn f1_is_nearest() {
+		for (x, y, z) in points() {
+			for &distance in &DISTANCES {
+				let cell = cellular2(x, y, distance);
+				assert!(cell.f1 <= cell.f2, "{:?} at {} {}", cell, x, y);
+				let cell = cellular3(x, y, z, distance);
+				assert!(cell.f1 <= cell.f2, "{:?} at {} {} {}", cell, x, y, z);
+			}
+		}
+	}
+
+	#[test]
+	fn same_seed_same_cells() {
+		let (a, b) = (Noise::new(5), Noise::new(5));
+		for (x, y, z) in points() {
+			assert_eq!(
+				a.cellular3(x, y, z, Distance::Euclidean),
+				b.cellular3(x, y, z, Distance::Euclidean)
+			);
+			assert_eq!(
+				a.cellular2(x, y, Distance::Manhattan).id,
+				b.cellular2(x, y, Distance::Manhattan).id
+			);
+		}
+	}
+
+	#[test]
+	fn distances_from_a_feature_point() {
+		// the feature point of the cell at the origin
+		let hash = REFERENCE.cell_hash(0, 0, 0);
+		let (x, y) = (hash[0] as f32 / 256.0, hash[1] as f32 / 256.0);
+		assert_eq!(cellular2(x, y, Distance::Euclidean).f1, 0.0);
+
+		let (x, y) = (x + 0.03, y - 0.04);
+		let f1 = |distance| cellular2(x, y, distance).f1;
+		assert!((f1(Distance::Euclidean) - 0.05).abs() < 1e-4);
+		assert!((f1(Distance::Manhattan) - 0.07).abs() < 1e-4);
+		assert!((f1(Distance::Chebyshev) - 0.04).abs() < 1e-4);
+	}
+
+	#[test]
+	fn f2_matches_brute_force() {
+		for (x, y, z) in points() {
+			for &distance in &DISTANCES {
+				let far = REFERENCE.search((x, y, 0.0), 4, 0, distance);
+				assert_eq!(cellular2(x, y, distance), far, "at {} {}", x, y);
+				let far = REFERENCE.search((x, y, z), 4, 4, distance);
+				assert_eq!(cellular3(x, y, z, distance), far, "at {} {} {}", x, y, z);
+			}
+		}
+	}
+
+	#[test]
+	fn measure() {
+		let d = [0.3, -0.4, 1.2];
+		// squared, the root is only taken for the nearest points
+		assert!((Distance::Euclidean.measure(d) - 1.69).abs() < 1e-6);
+		assert!((Distance::Manhattan.measure(d) - 1.9).abs() < 1e-6);
+		assert_eq!(Distance::Chebyshev.measure(d), 1.2);
+	}
+}
@@ -0,0 +1,128 @@
+//! Fractal combinations of noise, layering octaves of increasing frequency
+//!
+//! All of these take the noise as a function of `(x, y, z)`, so they work
+//! with every noise in this module. For 2D noise, ignore `z`, like
+//! `|x, y, _| simplex2(x, y)`.
+
+/// How the octaves are layered
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fractal {
+	/// Number of layers of noise
+	pub octaves:    u8,
+	/// Factor of the frequency from one octave to the next
+	pub lacunarity: f32,
+	/// Factor of the amplitude from one octave to the next
+	pub gain:       f32,
+}
+
+impl Default for Fractal {
+	fn default() -> Self {
+		Fractal {
+			octaves:    4,
+			lacunarity: 2.0,
+			gain:       0.5,
+		}
+	}
+}
+
+impl Fractal {
+	pub fn new(octaves: u8, lacunarity: f32, gain: f32) -> Self {
+		Fractal {
+			octaves,
+			lacunarity,
+			gain,
+		}
+	}
+
+	/// Sum `shape` of every octave, normalized by the total amplitude
+	fn sum<N, S>(&self, noise: N, x: f32, y: f32, z: f32, shape: S) -> f32
+	where
+		N: Fn(f32, f32, f32) -> f32,
+		S: Fn(f32) -> f32,
+	{
+		let mut total = 0.0;
+		let mut norm = 0.0;
+		let mut frequency = 1.0;
+		let mut amplitude = 1.0;
+		for _ in 0..self.octaves {
+			total += shape(noise(x * frequency, y * frequency, z * frequency)) * amplitude;
+			norm += amplitude;
+			frequency *= self.lacunarity;
+			amplitude *= self.gain;
+		}
+		if norm == 0.0 {
+			0.0
+		} else {
+			total / norm
+		}
+	}
+
+	/// Fractal Brownian motion, the plain sum of the octaves, from -1 to 1
+	pub fn fbm<N: Fn(f32, f32, f32) -> f32>(&self, noise: N, x: f32, y: f32, z: f32) -> f32 {
+		self.sum(noise, x, y, z, |n| n)
+	}
+
+	/// Sharp ridges where the noise crosses zero, from 0 to 1
+	pub fn ridged<N: Fn(f32, f32, f32) -> f32>(&self, noise: N, x: f32, y: f32, z: f32) -> f32 {
+		self.sum(noise, x, y, z, |n| {
+			let n = 1.0 - n.abs();
+			n * n
+		})
+	}
+
+	/// Sum of the absolute values of the octaves, billowy like smoke, from 0 to 1
+	pub fn turbulence<N: Fn(f32, f32, f32) -> f32>(&self, noise: N, x: f32, y: f32, z: f32) -> f32 {
+		self.sum(noise, x, y, z, |n| n.abs())
+	}
+}
+
+/// Domain warping, offsets the point by the noise itself before sampling it
+///
+/// `strength` is how far the point can move, in noise coordinates.
+pub fn warp<N: Fn(f32, f32, f32) -> f32>(noise: N, x: f32, y: f32, z: f32, strength: f32) -> f32 {
+	// arbitrary offsets, so the two displacements aren't correlated
+	let dx = noise(x + 5.2, y + 1.3, z);
+	let dy = noise(x + 1.7, y + 9.2, z);
+	noise(x + dx * strength, y + dy * strength, z)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::noise::simplex;
+
+	fn points() -> impl Iterator<Item = (f32, f32, f32)> {
+		(0..300).map(|i| {
+			let i = i as f32;
+			(i * 0.37 - 50.0, i * 0.71 - 90.0, i * 0.13)
+		})
+	}
+
+	#[test]
+	fn output_ranges() {
+		let fractal = Fractal::default();
+		for (x, y, z) in points() {
+			let n = fractal.fbm(simplex, x, y, z);
+			assert!((-1.0..=1.0).contains(&n), "fbm {}", n);
+			let n = fractal.ridged(simplex, x, y, z);
+			assert!((0.0..=1.0).contains(&n), "ridged {}", n);
+			let n = fractal.turbulence(simplex, x, y, z);
+			assert!((0.0..=1.0).contains(&n), "turbulence {}", n);
+			let n = warp(simplex, x, y, z, 2.0);
+			assert!((-1.0..=1.0).contains(&n), "warp {}", n);
+		}
+	}
+
+	#[test]
+	fn one_octave_is_the_base_noise() {
+		let fractal = Fractal::new(1, 2.0, 0.5);
+		for (x, y, z) in points() {
+			assert_eq!(fractal.fbm(simplex, x, y, z), simplex(x, y, z));
+		}
+	}
+
+	#[test]
+	fn no_octaves_is_flat() {
+		assert_eq!(Fractal::new(0, 2.0, 0.5).fbm(simplex, 0.3, 0.6, 0.9), 0.0);
+	}
+}
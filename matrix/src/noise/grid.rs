@@ -0,0 +1,144 @@
+//! Noise sampled over a whole grid at once
+//!
+//! Filling a frame samples the noise at evenly spaced points, so a lot of the
+//! work can be shared between neighbouring samples instead of starting from
+//! scratch for every pixel.
+
+use super::{fastfloor, simplex3_corners, wrap, Noise, REFERENCE};
+
+/// Evenly spaced sample points on a slice of constant `z`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Grid {
+	/// Noise coordinates of the top left sample
+	pub origin: (f32, f32),
+	/// Distance between neighbouring samples, in noise coordinates
+	pub step:   (f32, f32),
+	pub z:      f32,
+}
+
+impl Grid {
+	pub fn new(origin: (f32, f32), step: (f32, f32), z: f32) -> Self {
+		Grid { origin, step, z }
+	}
+}
+
+impl Noise {
+	/// 3D simplex noise for every point of a `width` x `height` grid
+	///
+	/// Calls `f` with the column, the row and the noise, row by row. The result
+	/// is the same as calling `simplex` for every point (up to rounding), but
+	/// the skew of y and z is done once per row, and most of the permutation
+	/// lookups are shared between neighbouring samples.
+	pub fn simplex_grid<F>(&self, grid: &Grid, width: usize, height: usize, mut f: F)
+	where
+		F: FnMut(usize, usize, f32),
+	{
+		let f3 = 1.0 / 3.0;
+		let g3 = 1.0 / 6.0;
+
+		// the hashes of the j and k coordinates of the current skewed cube, which
+		// usually stay the same for many samples in a row
+		let mut cached = None;
+		let mut hashes = [0; 4];
+
+		for row in 0..height {
+			let yin = grid.origin.1 + row as f32 * grid.step.1;
+			// the skew is linear, so y and z only have to be done once per row
+			let yz = (yin + grid.z) * f3;
+			for column in 0..width {
+				let xin = grid.origin.0 + column as f32 * grid.step.0;
+
+				let s = xin * f3 + yz;
+				let i = fastfloor(xin + s) as f32;
+				let j = fastfloor(yin + s) as f32;
+				let k = fastfloor(grid.z + s) as f32;
+
+				if cached != Some((j, k)) {
+					cached = Some((j, k));
+					let (jj, kk) = (wrap(j), wrap(k));
+					for (corner, hash) in hashes.iter_mut().enumerate() {
+						let (j1, k1) = (corner >> 1, corner & 1);
+						*hash = self.p(jj + j1 + self.p(kk + k1));
+					}
+				}
+
+				let t = (i + j + k) * g3;
+				let x0 = xin - (i - t);
+				let y0 = yin - (j - t);
+				let z0 = grid.z - (k - t);
+				let ii = wrap(i);
+				let n = simplex3_corners(x0, y0, z0, |i1, j1, k1| {
+					self.p(ii + i1 + hashes[j1 << 1 | k1]) % 12
+				});
+				f(column, row, n);
+			}
+		}
+	}
+
+	/// Fill `out` (row-major) with `simplex_grid`
+	pub fn fill_simplex<const W: usize, const H: usize>(
+		&self,
+		grid: &Grid,
+		out: &mut [[f32; W]; H],
+	) {
+		self.simplex_grid(grid, W, H, |x, y, n| out[y][x] = n);
+	}
+}
+
+/// `Noise::simplex_grid` with the reference permutation
+pub fn simplex_grid<F: FnMut(usize, usize, f32)>(grid: &Grid, width: usize, height: usize, f: F) {
+	REFERENCE.simplex_grid(grid, width, height, f)
+}
+
+/// `Noise::fill_simplex` with the reference permutation
+pub fn fill_simplex<const W: usize, const H: usize>(grid: &Grid, out: &mut [[f32; W]; H]) {
+	REFERENCE.fill_simplex(grid, out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::noise::simplex;
+
+	#[test]
+	fn grid_matches_simplex() {
+		let grids = [
+			Grid::new((0.0, 0.0), (0.25, 0.25), 0.0),
+			Grid::new((-7.3, 12.9), (0.31, 0.17), 3.7),
+			Grid::new((100.5, -40.25), (1.3, 0.9), -12.1),
+		];
+		for grid in &grids {
+			let mut count = 0;
+			simplex_grid(grid, 40, 20, |column, row, n| {
+				let x = grid.origin.0 + column as f32 * grid.step.0;
+				let y = grid.origin.1 + row as f32 * grid.step.1;
+				let expected = simplex(x, y, grid.z);
+				assert!(
+					(n - expected).abs() < 1e-5,
+					"{} != {} at {} {}",
+					n,
+					expected,
+					x,
+					y
+				);
+				count += 1;
+			});
+			assert_eq!(count, 40 * 20);
+		}
+	}
+
+	#[test]
+	fn seeded_grid_matches_seeded_simplex() {
+		let noise = Noise::new(99);
+		let grid = Grid::new((3.3, -1.1), (0.4, 0.6), 2.2);
+		let mut out = [[0.0; 16]; 8];
+		noise.fill_simplex(&grid, &mut out);
+		for (row, line) in out.iter().enumerate() {
+			for (column, &n) in line.iter().enumerate() {
+				let x = grid.origin.0 + column as f32 * grid.step.0;
+				let y = grid.origin.1 + row as f32 * grid.step.1;
+				assert!((n - noise.simplex(x, y, grid.z)).abs() < 1e-5);
+			}
+		}
+	}
+}
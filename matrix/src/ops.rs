@@ -0,0 +1,280 @@
+//! Post-processing of whole frames, like FastLED's `fadeToBlackBy` and `blur2d`
+//!
+//! Everything works on a `Framebuffer` in integer math, so it's cheap enough
+//! to run every frame, for trails, glows and mirrored patterns.
+
+use crate::{
+	framebuffer::Framebuffer,
+	math8::{qadd8, scale8, scale8_video},
+};
+use embedded_graphics::pixelcolor::{PixelColor, Rgb565, Rgb888, RgbColor};
+
+/// RGB colours that can be taken apart into channels and put back together
+pub trait Channels: RgbColor {
+	fn from_channels(r: u8, g: u8, b: u8) -> Self;
+}
+
+impl Channels for Rgb565 {
+	fn from_channels(r: u8, g: u8, b: u8) -> Self {
+		Rgb565::new(r, g, b)
+	}
+}
+
+impl Channels for Rgb888 {
+	fn from_channels(r: u8, g: u8, b: u8) -> Self {
+		Rgb888::new(r, g, b)
+	}
+}
+
+fn map<C: Channels>(colour: C, f: impl Fn(u8) -> u8) -> C {
+	C::from_channels(f(colour.r()), f(colour.g()), f(colour.b()))
+}
+
+fn add<C: Channels>(a: C, b: C) -> C {
+	C::from_channels(
+		qadd8(a.r(), b.r()).min(C::MAX_R),
+		qadd8(a.g(), b.g()).min(C::MAX_G),
+		qadd8(a.b(), b.b()).min(C::MAX_B),
+	)
+}
+
+/// Dim every pixel by `amount / 256`, from FastLED
+///
+/// Repeated fading always ends up at black.
+pub fn fade_to_black_by<C: Channels, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+	amount: u8,
+) {
+	let keep = 255 - amount;
+	for pixel in frame.pixels_mut().iter_mut().flatten() {
+		*pixel = map(*pixel, |c| scale8(c, keep));
+	}
+}
+
+/// Dim every pixel by `amount / 256`, but a lit channel never turns
+/// completely off, like FastLED's `fadeLightBy`
+pub fn fade_light_by<C: Channels, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+	amount: u8,
+) {
+	let keep = 255 - amount;
+	for pixel in frame.pixels_mut().iter_mut().flatten() {
+		*pixel = map(*pixel, |c| scale8_video(c, keep));
+	}
+}
+
+/// Blur every row horizontally, from FastLED
+///
+/// Every pixel keeps `255 - amount` of itself and spreads half of `amount`
+/// to each of its neighbours. 0 does nothing, 172 is a gentle blur.
+pub fn blur_rows<C: Channels, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+	amount: u8,
+) {
+	let keep = 255 - amount;
+	let seep = amount >> 1;
+	for row in frame.pixels_mut().iter_mut() {
+		let mut carryover = C::from_channels(0, 0, 0);
+		for x in 0..W {
+			let current = row[x];
+			let part = map(current, |c| scale8(c, seep));
+			if x > 0 {
+				row[x - 1] = add(row[x - 1], part);
+			}
+			row[x] = add(map(current, |c| scale8(c, keep)), carryover);
+			carryover = part;
+		}
+	}
+}
+
+/// Blur every column vertically, see `blur_rows`
+pub fn blur_columns<C: Channels, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+	amount: u8,
+) {
+	let keep = 255 - amount;
+	let seep = amount >> 1;
+	let pixels = frame.pixels_mut();
+	// the frame is stored by rows, so a column can only be walked by index
+	#[allow(clippy::needless_range_loop)]
+	for x in 0..W {
+		let mut carryover = C::from_channels(0, 0, 0);
+		for y in 0..H {
+			let current = pixels[y][x];
+			let part = map(current, |c| scale8(c, seep));
+			if y > 0 {
+				pixels[y - 1][x] = add(pixels[y - 1][x], part);
+			}
+			pixels[y][x] = add(map(current, |c| scale8(c, keep)), carryover);
+			carryover = part;
+		}
+	}
+}
+
+/// Blur in both directions, see `blur_rows`
+pub fn blur2d<C: Channels, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+	amount: u8,
+) {
+	blur_rows(frame, amount);
+	blur_columns(frame, amount);
+}
+
+/// Move the frame by `dx`, `dy` pixels, filling the uncovered pixels with `fill`
+pub fn shift<C: PixelColor, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+	dx: i32,
+	dy: i32,
+	fill: C,
+) {
+	// go against the direction of the shift, so every pixel is read before
+	// it gets overwritten
+	let pixels = frame.pixels_mut();
+	for i in 0..H {
+		let y = if dy > 0 { H - 1 - i } else { i };
+		for j in 0..W {
+			let x = if dx > 0 { W - 1 - j } else { j };
+			let sx = x as i32 - dx;
+			let sy = y as i32 - dy;
+			pixels[y][x] = if sx < 0 || sy < 0 || sx >= W as i32 || sy >= H as i32 {
+				fill
+			} else {
+				pixels[sy as usize][sx as usize]
+			};
+		}
+	}
+}
+
+/// Move the frame by `dx`, `dy` pixels, wrapping around the edges
+pub fn scroll<C: PixelColor, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+	dx: i32,
+	dy: i32,
+) {
+	let dx = dx.rem_euclid(W as i32) as usize;
+	let dy = dy.rem_euclid(H as i32) as usize;
+	let pixels = frame.pixels_mut();
+	pixels.rotate_right(dy);
+	for row in pixels.iter_mut() {
+		row.rotate_right(dx);
+	}
+}
+
+/// Mirror the left half onto the right half
+pub fn mirror_x<C: PixelColor, const W: usize, const H: usize>(frame: &mut Framebuffer<C, W, H>) {
+	for row in frame.pixels_mut().iter_mut() {
+		for x in 0..W / 2 {
+			row[W - 1 - x] = row[x];
+		}
+	}
+}
+
+/// Mirror the top half onto the bottom half
+pub fn mirror_y<C: PixelColor, const W: usize, const H: usize>(frame: &mut Framebuffer<C, W, H>) {
+	let pixels = frame.pixels_mut();
+	for y in 0..H / 2 {
+		pixels[H - 1 - y] = pixels[y];
+	}
+}
+
+/// Mirror the top left quarter into the other three, like a kaleidoscope
+pub fn kaleidoscope<C: PixelColor, const W: usize, const H: usize>(
+	frame: &mut Framebuffer<C, W, H>,
+) {
+	mirror_x(frame);
+	mirror_y(frame);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fade_to_black_reaches_black() {
+		let mut frame = Framebuffer::<Rgb565, 2, 2>::new(Rgb565::WHITE);
+		for _ in 0..32 {
+			fade_to_black_by(&mut frame, 64);
+		}
+		assert!(frame
+			.pixels()
+			.iter()
+			.flatten()
+			.all(|&pixel| pixel == Rgb565::BLACK));
+	}
+
+	#[test]
+	fn fade_light_keeps_lit_pixels() {
+		let mut frame = Framebuffer::<Rgb565, 2, 2>::new(Rgb565::WHITE);
+		for _ in 0..32 {
+			fade_light_by(&mut frame, 64);
+		}
+		assert!(frame
+			.pixels()
+			.iter()
+			.flatten()
+			.all(|&pixel| pixel != Rgb565::BLACK));
+	}
+
+	/// A 4x3 frame, every pixel with its own colour
+	fn numbered() -> Framebuffer<Rgb888, 4, 3> {
+		let mut frame = Framebuffer::new(Rgb888::BLACK);
+		for (y, row) in frame.pixels_mut().iter_mut().enumerate() {
+			for (x, pixel) in row.iter_mut().enumerate() {
+				*pixel = Rgb888::new(x as u8, y as u8, 1);
+			}
+		}
+		frame
+	}
+
+	fn at(frame: &Framebuffer<Rgb888, 4, 3>, x: usize, y: usize) -> (u8, u8, u8) {
+		let pixel = frame.pixels()[y][x];
+		(pixel.r(), pixel.g(), pixel.b())
+	}
+
+	#[test]
+	fn shift_fills_the_uncovered_pixels() {
+		let mut frame = numbered();
+		shift(&mut frame, 1, -1, Rgb888::BLACK);
+		assert_eq!(at(&frame, 0, 0), (0, 0, 0));
+		assert_eq!(at(&frame, 1, 0), (0, 1, 1));
+		assert_eq!(at(&frame, 3, 1), (2, 2, 1));
+		assert_eq!(at(&frame, 2, 2), (0, 0, 0));
+
+		let mut frame = numbered();
+		shift(&mut frame, -2, 1, Rgb888::BLACK);
+		assert_eq!(at(&frame, 0, 1), (2, 0, 1));
+		assert_eq!(at(&frame, 1, 2), (3, 1, 1));
+		assert_eq!(at(&frame, 2, 1), (0, 0, 0));
+		assert_eq!(at(&frame, 0, 0), (0, 0, 0));
+	}
+
+	#[test]
+	fn scroll_wraps_around() {
+		let mut frame = numbered();
+		scroll(&mut frame, 1, -1);
+		assert_eq!(at(&frame, 0, 0), (3, 1, 1));
+		assert_eq!(at(&frame, 1, 2), (0, 0, 1));
+
+		// scrolling by the size of the frame changes nothing
+		let mut frame = numbered();
+		scroll(&mut frame, -4, 3);
+		assert_eq!(frame, numbered());
+	}
+
+	#[test]
+	fn mirror() {
+		let mut frame = numbered();
+		mirror_x(&mut frame);
+		assert_eq!(at(&frame, 3, 1), (0, 1, 1));
+		assert_eq!(at(&frame, 2, 1), (1, 1, 1));
+
+		let mut frame = numbered();
+		mirror_y(&mut frame);
+		assert_eq!(at(&frame, 2, 2), (2, 0, 1));
+		assert_eq!(at(&frame, 2, 1), (2, 1, 1));
+
+		let mut frame = numbered();
+		kaleidoscope(&mut frame);
+		assert_eq!(at(&frame, 3, 2), (0, 0, 1));
+	}
+}
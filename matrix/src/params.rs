@@ -0,0 +1,231 @@
+/// The value of an effect parameter
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParamValue {
+	Float(f32),
+	Int(i32),
+	Bool(bool),
+}
+
+impl ParamValue {
+	pub fn as_float(self) -> Option<f32> {
+		match self {
+			ParamValue::Float(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	pub fn as_int(self) -> Option<i32> {
+		match self {
+			ParamValue::Int(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(self) -> Option<bool> {
+		match self {
+			ParamValue::Bool(value) => Some(value),
+			_ => None,
+		}
+	}
+}
+
+/// The type of an effect parameter, and the range of values it accepts (inclusive)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParamKind {
+	Float { min: f32, max: f32 },
+	Int { min: i32, max: i32 },
+	Bool,
+}
+
+/// Describes one tunable parameter of an effect
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Param {
+	pub name:    &'static str,
+	pub kind:    ParamKind,
+	pub default: ParamValue,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParamError {
+	/// The effect has no parameter with this name
+	Unknown,
+	/// The value has a different type than the parameter
+	WrongType,
+	/// The value is outside of the parameter's range
+	OutOfRange,
+}
+
+impl Param {
+	/// Check that `value` has the right type and is inside the range
+	pub fn check(&self, value: ParamValue) -> Result<ParamValue, ParamError> {
+		let in_range = match (self.kind, value) {
+			(ParamKind::Float { min, max }, ParamValue::Float(v)) => v >= min && v <= max,
+			(ParamKind::Int { min, max }, ParamValue::Int(v)) => v >= min && v <= max,
+			(ParamKind::Bool, ParamValue::Bool(_)) => true,
+			_ => return Err(ParamError::WrongType),
+		};
+		if in_range {
+			Ok(value)
+		} else {
+			Err(ParamError::OutOfRange)
+		}
+	}
+}
+
+/// Look up `name` in `params` and check `value` against it
+///
+/// Meant for implementing `Effect::set`.
+pub fn check(params: &[Param], name: &str, value: ParamValue) -> Result<ParamValue, ParamError> {
+	params
+		.iter()
+		.find(|param| param.name == name)
+		.ok_or(ParamError::Unknown)?
+		.check(value)
+}
+
+/// The current values of a fixed list of parameters, for effects that don't
+/// keep them in their own fields
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Values<const N: usize> {
+	params: &'static [Param; N],
+	values: [ParamValue; N],
+}
+
+impl<const N: usize> Values<N> {
+	/// Start with the default of every parameter
+	pub fn new(params: &'static [Param; N]) -> Self {
+		let mut values = [ParamValue::Bool(false); N];
+		for (value, param) in values.iter_mut().zip(params.iter()) {
+			*value = param.default;
+		}
+		Values { params, values }
+	}
+
+	pub fn params(&self) -> &'static [Param] {
+		self.params
+	}
+
+	pub fn get(&self, name: &str) -> Option<ParamValue> {
+		let index = self.params.iter().position(|param| param.name == name)?;
+		Some(self.values[index])
+	}
+
+	pub fn set(&mut self, name: &str, value: ParamValue) -> Result<(), ParamError> {
+		let value = check(self.params, name, value)?;
+		let index = self
+			.params
+			.iter()
+			.position(|param| param.name == name)
+			.unwrap();
+		self.values[index] = value;
+		Ok(())
+	}
+
+	/// The value of the parameter at `index` in `params`, without searching
+	/// for its name, for code that runs for every pixel
+	pub fn at(&self, index: usize) -> ParamValue {
+		self.values[index]
+	}
+
+	/// The value of a float parameter, panics if there is none called `name`
+	pub fn float(&self, name: &str) -> f32 {
+		self.get(name).and_then(ParamValue::as_float).unwrap()
+	}
+
+	/// The value of an int parameter, panics if there is none called `name`
+	pub fn int(&self, name: &str) -> i32 {
+		self.get(name).and_then(ParamValue::as_int).unwrap()
+	}
+
+	/// The value of a bool parameter, panics if there is none called `name`
+	pub fn bool(&self, name: &str) -> bool {
+		self.get(name).and_then(ParamValue::as_bool).unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PARAMS: &[Param; 3] = &[
+		Param {
+			name:    "speed",
+			kind:    ParamKind::Float {
+				min: 0.0,
+				max: 10.0,
+			},
+			default: ParamValue::Float(1.0),
+		},
+		Param {
+			name:    "size",
+			kind:    ParamKind::Int { min: 1, max: 16 },
+			default: ParamValue::Int(4),
+		},
+		Param {
+			name:    "mirror",
+			kind:    ParamKind::Bool,
+			default: ParamValue::Bool(false),
+		},
+	];
+
+	#[test]
+	fn check_accepts_values_in_range() {
+		assert_eq!(
+			check(PARAMS, "speed", ParamValue::Float(0.0)),
+			Ok(ParamValue::Float(0.0))
+		);
+		assert_eq!(
+			check(PARAMS, "speed", ParamValue::Float(10.0)),
+			Ok(ParamValue::Float(10.0))
+		);
+		assert_eq!(
+			check(PARAMS, "size", ParamValue::Int(16)),
+			Ok(ParamValue::Int(16))
+		);
+		assert_eq!(
+			check(PARAMS, "mirror", ParamValue::Bool(true)),
+			Ok(ParamValue::Bool(true))
+		);
+	}
+
+	#[test]
+	fn check_errors() {
+		assert_eq!(
+			check(PARAMS, "colour", ParamValue::Int(1)),
+			Err(ParamError::Unknown)
+		);
+		assert_eq!(
+			check(PARAMS, "speed", ParamValue::Int(1)),
+			Err(ParamError::WrongType)
+		);
+		assert_eq!(
+			check(PARAMS, "mirror", ParamValue::Float(1.0)),
+			Err(ParamError::WrongType)
+		);
+		assert_eq!(
+			check(PARAMS, "speed", ParamValue::Float(10.5)),
+			Err(ParamError::OutOfRange)
+		);
+		assert_eq!(
+			check(PARAMS, "size", ParamValue::Int(0)),
+			Err(ParamError::OutOfRange)
+		);
+	}
+
+	#[test]
+	fn values() {
+		let mut values = Values::new(PARAMS);
+		assert_eq!(values.float("speed"), 1.0);
+		assert_eq!(values.int("size"), 4);
+		assert!(!values.bool("mirror"));
+
+		assert_eq!(values.set("size", ParamValue::Int(8)), Ok(()));
+		assert_eq!(values.get("size"), Some(ParamValue::Int(8)));
+		assert_eq!(
+			values.set("size", ParamValue::Int(17)),
+			Err(ParamError::OutOfRange)
+		);
+		assert_eq!(values.get("size"), Some(ParamValue::Int(8)));
+		assert_eq!(values.get("colour"), None);
+	}
+}
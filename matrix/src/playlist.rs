@@ -0,0 +1,342 @@
+use crate::{
+	params::{Param, ParamError, ParamValue},
+	transition::{Transition, TransitionKind},
+	Effect,
+};
+use core::time::Duration;
+use embedded_graphics::{pixelcolor::Rgb565, DrawTarget};
+
+/// An effect in a playlist, and how long it plays before switching to the next one
+pub struct Entry<E> {
+	pub effect:   E,
+	pub duration: Duration,
+}
+
+impl<E> Entry<E> {
+	pub fn new(effect: E, duration: Duration) -> Self {
+		Entry { effect, duration }
+	}
+}
+
+/// Plays a fixed list of effects one after another
+///
+/// All entries share one effect type, so mixing different effects needs
+/// an enum implementing `Effect` over them. Everything is stored inline,
+/// no allocation is needed.
+///
+/// Only the current effect is stepped, the others keep their state until
+/// they get their turn again. While a transition is running, the outgoing
+/// effect keeps being stepped as well.
+pub struct Playlist<E, const N: usize> {
+	entries:    [Entry<E>; N],
+	order:      [usize; N],
+	position:   usize,
+	elapsed:    Duration,
+	shuffle:    bool,
+	repeat:     bool,
+	rng:        u32,
+	transition: Transition,
+	outgoing:   usize,
+}
+
+impl<E: Effect, const N: usize> Playlist<E, N> {
+	/// Create a playlist, starting at the first entry
+	///
+	/// Plays in order, repeats and cuts between effects by default.
+	pub fn new(entries: [Entry<E>; N]) -> Self {
+		assert!(N > 0);
+		let mut order = [0; N];
+		for (i, index) in order.iter_mut().enumerate() {
+			*index = i;
+		}
+		Playlist {
+			entries,
+			order,
+			position: 0,
+			elapsed: Duration::from_secs(0),
+			shuffle: false,
+			repeat: true,
+			rng: 0x2545_f491,
+			transition: Transition::new(TransitionKind::Cut, Duration::from_secs(0)),
+			outgoing: 0,
+		}
+	}
+
+	/// How to switch from one effect to the next
+	pub fn set_transition(&mut self, kind: TransitionKind, duration: Duration) {
+		self.transition.set_kind(kind);
+		self.transition.set_duration(duration);
+	}
+
+	/// Play the entries in a random order, reshuffled after every round
+	///
+	/// The current entry keeps playing.
+	pub fn set_shuffle(&mut self, shuffle: bool) {
+		let current = self.index();
+		self.shuffle = shuffle;
+		if shuffle {
+			self.shuffle_order();
+		} else {
+			for (i, index) in self.order.iter_mut().enumerate() {
+				*index = i;
+			}
+		}
+		self.position = self.order.iter().position(|&i| i == current).unwrap_or(0);
+	}
+
+	pub fn shuffle(&self) -> bool {
+		self.shuffle
+	}
+
+	/// Start over after the last entry, otherwise the last entry keeps playing
+	pub fn set_repeat(&mut self, repeat: bool) {
+		self.repeat = repeat;
+	}
+
+	pub fn repeat(&self) -> bool {
+		self.repeat
+	}
+
+	/// Seed the random number generator used for shuffling
+	///
+	/// A seed of 0 is replaced, the generator would get stuck otherwise.
+	pub fn set_seed(&mut self, seed: u32) {
+		self.rng = if seed == 0 { 0x2545_f491 } else { seed };
+	}
+
+	/// Index of the entry currently playing
+	pub fn index(&self) -> usize {
+		self.order[self.position]
+	}
+
+	pub fn current(&self) -> &E {
+		&self.entries[self.index()].effect
+	}
+
+	pub fn current_mut(&mut self) -> &mut E {
+		let index = self.index();
+		&mut self.entries[index].effect
+	}
+
+	pub fn entries(&self) -> &[Entry<E>; N] {
+		&self.entries
+	}
+
+	pub fn entries_mut(&mut self) -> &mut [Entry<E>; N] {
+		&mut self.entries
+	}
+
+	/// Switch to the next entry, wrapping around after the last one
+	pub fn next(&mut self) {
+		let outgoing = self.index();
+		if self.position + 1 < N {
+			self.position += 1;
+		} else {
+			self.restart();
+		}
+		self.switched(outgoing);
+	}
+
+	/// Switch to the previous entry, wrapping around before the first one
+	pub fn previous(&mut self) {
+		let outgoing = self.index();
+		self.position = if self.position == 0 {
+			N - 1
+		} else {
+			self.position - 1
+		};
+		self.switched(outgoing);
+	}
+
+	/// Switch to the entry at `index` in the list
+	pub fn select(&mut self, index: usize) {
+		assert!(index < N);
+		let outgoing = self.index();
+		self.position = self.order.iter().position(|&i| i == index).unwrap_or(0);
+		self.switched(outgoing);
+	}
+
+	fn switched(&mut self, outgoing: usize) {
+		self.elapsed = Duration::from_secs(0);
+		if outgoing != self.index() {
+			self.outgoing = outgoing;
+			let current = self.index();
+			self.transition.start(
+				&self.entries[outgoing].effect,
+				&self.entries[current].effect,
+			);
+		}
+	}
+
+	/// Begin a new round, with a new order if shuffling
+	fn restart(&mut self) {
+		if self.shuffle {
+			let last = self.index();
+			self.shuffle_order();
+			// Don't play the same effect twice in a row
+			if N > 1 && self.order[0] == last {
+				self.order.swap(0, N - 1);
+			}
+		}
+		self.position = 0;
+	}
+
+	// Fisher-Yates
+	fn shuffle_order(&mut self) {
+		for i in (1..N).rev() {
+			let j = self.random() as usize % (i + 1);
+			self.order.swap(i, j);
+		}
+	}
+
+	// xorshift32
+	fn random(&mut self) -> u32 {
+		let mut x = self.rng;
+		x ^= x << 13;
+		x ^= x >> 17;
+		x ^= x << 5;
+		self.rng = x;
+		x
+	}
+}
+
+impl<E: Effect, const N: usize> Effect for Playlist<E, N> {
+	fn step(&mut self, delta: Duration) {
+		self.current_mut().step(delta);
+		if self.transition.is_active() {
+			self.entries[self.outgoing].effect.step(delta);
+		}
+
+		self.elapsed += delta;
+		if self.elapsed >= self.entries[self.index()].duration {
+			if self.position + 1 < N || self.repeat {
+				self.next();
+			} else {
+				// Stay on the last entry
+				self.elapsed = Duration::from_secs(0);
+			}
+		}
+
+		let current = self.index();
+		self.transition.step(
+			delta,
+			&self.entries[self.outgoing].effect,
+			&self.entries[current].effect,
+		);
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		if self.transition.is_active() {
+			self.transition.render(target)
+		} else {
+			self.current().render(target)
+		}
+	}
+
+	/// The parameters of the current effect
+	fn params(&self) -> &'static [Param] {
+		self.current().params()
+	}
+
+	fn get(&self, name: &str) -> Option<ParamValue> {
+		self.current().get(name)
+	}
+
+	fn set(&mut self, name: &str, value: ParamValue) -> Result<(), ParamError> {
+		self.current_mut().set(name, value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Does nothing, the playlist only cares about the order
+	struct Idle;
+
+	impl Effect for Idle {
+		fn step(&mut self, _delta: Duration) {}
+
+		fn render<D: DrawTarget<Rgb565>>(&self, _target: &mut D) -> Result<(), D::Error> {
+			Ok(())
+		}
+	}
+
+	fn playlist<const N: usize>() -> Playlist<Idle, N> {
+		let mut entries = [(); N].map(|_| Entry::new(Idle, Duration::from_secs(1)));
+		entries[0].duration = Duration::from_secs(2);
+		Playlist::new(entries)
+	}
+
+	#[test]
+	fn next_and_previous_wrap_around() {
+		let mut playlist = playlist::<3>();
+		assert_eq!(playlist.index(), 0);
+		playlist.next();
+		assert_eq!(playlist.index(), 1);
+		playlist.next();
+		playlist.next();
+		assert_eq!(playlist.index(), 0);
+		playlist.previous();
+		assert_eq!(playlist.index(), 2);
+		playlist.select(1);
+		assert_eq!(playlist.index(), 1);
+	}
+
+	#[test]
+	fn advances_after_the_duration() {
+		let mut playlist = playlist::<3>();
+		playlist.step(Duration::from_millis(1500));
+		assert_eq!(playlist.index(), 0);
+		playlist.step(Duration::from_millis(500));
+		assert_eq!(playlist.index(), 1);
+		playlist.step(Duration::from_secs(1));
+		assert_eq!(playlist.index(), 2);
+	}
+
+	#[test]
+	fn repeat() {
+		let mut playlist = playlist::<3>();
+		playlist.select(2);
+		playlist.step(Duration::from_secs(1));
+		assert_eq!(playlist.index(), 0);
+
+		playlist.set_repeat(false);
+		playlist.select(2);
+		playlist.step(Duration::from_secs(1));
+		playlist.step(Duration::from_secs(1));
+		assert_eq!(playlist.index(), 2);
+	}
+
+	#[test]
+	fn shuffle_plays_every_entry_once_per_round() {
+		let mut playlist = playlist::<8>();
+		playlist.set_seed(1234);
+		playlist.set_shuffle(true);
+		assert_eq!(playlist.index(), 0);
+
+		// the current entry keeps playing, somewhere in the first round
+		while playlist.position != 0 {
+			playlist.next();
+		}
+		for _ in 0..4 {
+			let mut seen = [false; 8];
+			let mut order = [0; 8];
+			for slot in order.iter_mut() {
+				let index = playlist.index();
+				assert!(!seen[index]);
+				seen[index] = true;
+				*slot = index;
+				playlist.next();
+			}
+			assert_ne!(order, [0, 1, 2, 3, 4, 5, 6, 7]);
+			// a new round never starts with the entry that ended the last one
+			assert_ne!(playlist.index(), order[7]);
+		}
+
+		playlist.set_shuffle(false);
+		let current = playlist.index();
+		playlist.next();
+		assert_eq!(playlist.index(), (current + 1) % 8);
+	}
+}
@@ -0,0 +1,285 @@
+//! The main loop shared by all boards
+//!
+//! A board only has to hand over its pins, a delay, a timer and a tick source
+//! by implementing `Board`. The `Runner` then scans out the panel whenever the
+//! timer fires, and steps the effect at a fixed frame rate in between.
+//!
+//! Boards that can refresh from an interrupt should `split` the runner
+//! instead, so a slow frame can't make the panel flicker.
+
+use crate::{
+	framebuffer::Framebuffer,
+	hub75::{Hub75, Outputs},
+	Effect,
+};
+use core::time::Duration;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_hal::{blocking::delay::DelayUs, timer::CountDown};
+
+/// A whole frame, as effects render it
+pub type Frame = Framebuffer<Rgb565, 64, 32>;
+
+/// A free running counter, used to measure the time between effect steps
+pub trait Ticks {
+	/// The current count, wrapping around at `u32::MAX`
+	fn now(&mut self) -> u32;
+
+	/// Counts per second
+	fn frequency(&self) -> u32;
+}
+
+/// Everything the runner needs from a board
+pub trait Board: Sized {
+	type Outputs: Outputs;
+	type Delay: DelayUs<u8>;
+	/// Paces the refresh of the panel, `Continuous` to refresh as fast as possible
+	type Timer: CountDown;
+	type Ticks: Ticks;
+
+	/// Brightness bits of the panel, see `Hub75::new`
+	const BRIGHTNESS_BITS: u8 = 3;
+
+	/// Set up the clocks and pins
+	///
+	/// The timer has to be started already, periodic at the refresh rate.
+	fn split(self) -> Parts<Self>;
+}
+
+/// The peripherals of a board, as handed to the runner
+pub struct Parts<B: Board> {
+	pub outputs: B::Outputs,
+	pub delay:   B::Delay,
+	pub timer:   B::Timer,
+	pub ticks:   B::Ticks,
+}
+
+/// A timer that is always due, to refresh the panel as fast as possible
+pub struct Continuous;
+
+impl CountDown for Continuous {
+	type Time = ();
+
+	fn start<T>(&mut self, _count: T)
+	where
+		T: Into<()>,
+	{
+	}
+
+	fn wait(&mut self) -> nb::Result<(), void::Void> {
+		Ok(())
+	}
+}
+
+/// The panel and the delay it needs for scanning out
+pub struct Display<O: Outputs, D> {
+	pub matrix: Hub75<O>,
+	delay:      D,
+}
+
+impl<O: Outputs, D: DelayUs<u8>> Display<O, D> {
+	pub fn new(matrix: Hub75<O>, delay: D) -> Self {
+		Display { matrix, delay }
+	}
+
+	/// Scan out the current frame once
+	pub fn refresh(&mut self) {
+		self.matrix.output(&mut self.delay);
+	}
+}
+
+/// Steps an effect at a fixed frame rate, measuring the real time between steps
+pub struct Stepper<E, T> {
+	pub effect: E,
+	ticks:      T,
+	period:     u32,
+	last:       u32,
+}
+
+impl<E: Effect, T: Ticks> Stepper<E, T> {
+	pub fn new(effect: E, mut ticks: T, fps: u32) -> Self {
+		let period = ticks.frequency() / fps.max(1);
+		let last = ticks.now();
+		Stepper {
+			effect,
+			ticks,
+			period,
+			last,
+		}
+	}
+
+	/// Whether a frame period has passed since the last step
+	pub fn due(&mut self) -> bool {
+		self.ticks.now().wrapping_sub(self.last) >= self.period
+	}
+
+	/// Step the effect by the time passed since the last step, and return it
+	pub fn step(&mut self) -> Duration {
+		let now = self.ticks.now();
+		let elapsed = now.wrapping_sub(self.last) as u64;
+		self.last = now;
+		let delta = Duration::from_micros(elapsed * 1_000_000 / self.ticks.frequency() as u64);
+		self.effect.step(delta);
+		delta
+	}
+}
+
+/// Runs an effect on a board
+pub struct Runner<B: Board, E> {
+	display: Display<B::Outputs, B::Delay>,
+	stepper: Stepper<E, B::Ticks>,
+	timer:   B::Timer,
+	back:    Frame,
+}
+
+/// A runner taken apart by `Runner::split`
+pub struct Split<B: Board, E> {
+	pub display: Display<B::Outputs, B::Delay>,
+	pub stepper: Stepper<E, B::Ticks>,
+	pub timer:   B::Timer,
+	/// For rendering the effect into, before converting it with `Hub75::blit`
+	/// or `Scan::blit`
+	pub back:    Frame,
+}
+
+impl<B: Board, E: Effect> Runner<B, E> {
+	/// Set up the board, stepping `effect` `fps` times per second
+	pub fn new(board: B, effect: E, fps: u32) -> Self {
+		let parts = board.split();
+		let matrix = Hub75::new(parts.outputs, B::BRIGHTNESS_BITS);
+		Runner {
+			display: Display::new(matrix, parts.delay),
+			stepper: Stepper::new(effect, parts.ticks, fps),
+			timer:   parts.timer,
+			back:    Framebuffer::new(Rgb565::BLACK),
+		}
+	}
+
+	/// Take the runner apart, for schedulers like RTIC that run the refresh
+	/// and the steps as separate tasks
+	pub fn split(self) -> Split<B, E> {
+		Split {
+			display: self.display,
+			stepper: self.stepper,
+			timer:   self.timer,
+			back:    self.back,
+		}
+	}
+
+	pub fn effect(&self) -> &E {
+		&self.stepper.effect
+	}
+
+	pub fn effect_mut(&mut self) -> &mut E {
+		&mut self.stepper.effect
+	}
+
+	/// Do whatever is due: refresh the panel if the timer fired, then step and
+	/// render the effect if a frame period has passed
+	pub fn poll(&mut self) {
+		if self.timer.wait().is_ok() {
+			self.display.refresh();
+		}
+		if self.stepper.due() {
+			self.stepper.step();
+			self.render();
+		}
+	}
+
+	/// Render the effect into the back buffer, and convert it all at once
+	fn render(&mut self) {
+		self.stepper.effect.render(&mut self.back).unwrap();
+		self.display.matrix.blit(&self.back);
+	}
+
+	pub fn run(mut self) -> ! {
+		self.render();
+		loop {
+			self.poll();
+		}
+	}
+}
+
+/// How long producing the frames took, compared to the time there is for one
+#[derive(Copy, Clone, Debug)]
+pub struct FrameStats {
+	pub frames:   u32,
+	/// Frames that took longer than the budget
+	pub overruns: u32,
+	pub min_us:   u32,
+	pub max_us:   u32,
+	total_us:     u64,
+	budget_us:    u32,
+}
+
+impl FrameStats {
+	pub fn new(budget: Duration) -> Self {
+		FrameStats {
+			frames:    0,
+			overruns:  0,
+			min_us:    u32::MAX,
+			max_us:    0,
+			total_us:  0,
+			budget_us: budget.as_micros() as u32,
+		}
+	}
+
+	pub fn record(&mut self, time: Duration) {
+		let time = time.as_micros() as u32;
+		self.frames += 1;
+		self.total_us += time as u64;
+		self.min_us = self.min_us.min(time);
+		self.max_us = self.max_us.max(time);
+		if time > self.budget_us {
+			self.overruns += 1;
+		}
+	}
+
+	pub fn average_us(&self) -> u32 {
+		(self.total_us / self.frames.max(1) as u64) as u32
+	}
+
+	/// Start over, keeping the budget
+	pub fn reset(&mut self) {
+		*self = FrameStats::new(Duration::from_micros(self.budget_us as u64));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn frame_stats_record() {
+		let mut stats = FrameStats::new(Duration::from_millis(10));
+		assert_eq!(stats.average_us(), 0);
+
+		for &ms in &[4, 12, 8] {
+			stats.record(Duration::from_millis(ms));
+		}
+		assert_eq!(stats.frames, 3);
+		assert_eq!(stats.min_us, 4_000);
+		assert_eq!(stats.max_us, 12_000);
+		assert_eq!(stats.average_us(), 8_000);
+		assert_eq!(stats.overruns, 1);
+
+		// exactly on budget is not an overrun
+		stats.record(Duration::from_millis(10));
+		assert_eq!(stats.overruns, 1);
+	}
+
+	#[test]
+	fn frame_stats_reset_keeps_the_budget() {
+		let mut stats = FrameStats::new(Duration::from_millis(10));
+		stats.record(Duration::from_millis(20));
+		stats.reset();
+		assert_eq!(stats.frames, 0);
+		assert_eq!(stats.overruns, 0);
+		assert_eq!(stats.max_us, 0);
+		assert_eq!(stats.min_us, u32::MAX);
+
+		stats.record(Duration::from_millis(11));
+		stats.record(Duration::from_millis(9));
+		assert_eq!(stats.overruns, 1);
+		assert_eq!((stats.min_us, stats.max_us), (9_000, 11_000));
+	}
+}
@@ -0,0 +1,236 @@
+//! Effects written as a function of the pixel position and time
+//!
+//! Plasmas, gradients and most noise effects only need to pick a colour for
+//! every pixel. `ShaderEffect` does the rest: it keeps the time, calls the
+//! shader for every pixel on `step`, and draws the result on `render`.
+
+use crate::{
+	colour::HSV,
+	framebuffer::Framebuffer,
+	params::{Param, ParamError, ParamKind, ParamValue, Values},
+	Effect,
+	HEIGHT,
+	WIDTH,
+};
+use core::time::Duration;
+use embedded_graphics::{
+	pixelcolor::{Rgb565, RgbColor},
+	DrawTarget,
+};
+use libm::{sinf, sqrtf};
+
+/// Picks the colour of one pixel
+///
+/// Gets the position in pixels, the time in seconds since the effect started
+/// and the current parameter values. Implemented for closures too, so
+/// `|x, y, t, params| ...` works as a shader.
+///
+/// `shade` runs for every pixel, so it should read the parameters with
+/// `Values::at`, looking them up by name searches the list every time.
+pub trait Shader<const N: usize> {
+	type Colour;
+
+	fn shade(&self, x: f32, y: f32, t: f32, params: &Values<N>) -> Self::Colour;
+}
+
+impl<F, C, const N: usize> Shader<N> for F
+where
+	F: Fn(f32, f32, f32, &Values<N>) -> C,
+{
+	type Colour = C;
+
+	fn shade(&self, x: f32, y: f32, t: f32, params: &Values<N>) -> C {
+		self(x, y, t, params)
+	}
+}
+
+/// An effect that runs a `Shader` for every pixel
+///
+/// The parameters are described by the `&'static [Param; N]` passed to `new`,
+/// and are exposed through `Effect::params`, `get` and `set` like those of
+/// any other effect.
+pub struct ShaderEffect<S, const N: usize> {
+	shader: S,
+	values: Values<N>,
+	time:   Duration,
+	frame:  Framebuffer<Rgb565, WIDTH, HEIGHT>,
+}
+
+impl<S: Shader<N>, const N: usize> ShaderEffect<S, N>
+where
+	Rgb565: From<S::Colour>,
+{
+	pub fn new(shader: S, params: &'static [Param; N]) -> Self {
+		ShaderEffect {
+			shader,
+			values: Values::new(params),
+			time: Duration::from_secs(0),
+			frame: Framebuffer::new(Rgb565::BLACK),
+		}
+	}
+
+	pub fn shader(&self) -> &S {
+		&self.shader
+	}
+
+	pub fn shader_mut(&mut self) -> &mut S {
+		&mut self.shader
+	}
+
+	/// Seconds since the effect started
+	///
+	/// The time is kept exact, but as an `f32` it loses precision as it
+	/// grows, to about 10ms after a day of running.
+	pub fn time(&self) -> f32 {
+		self.time.as_secs_f32()
+	}
+}
+
+impl<S: Shader<N>, const N: usize> Effect for ShaderEffect<S, N>
+where
+	Rgb565: From<S::Colour>,
+{
+	fn step(&mut self, delta: Duration) {
+		self.time += delta;
+		let t = self.time();
+		for (y, row) in self.frame.pixels_mut().iter_mut().enumerate() {
+			for (x, pixel) in row.iter_mut().enumerate() {
+				let colour = self.shader.shade(x as f32, y as f32, t, &self.values);
+				*pixel = colour.into();
+			}
+		}
+	}
+
+	fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		self.frame.render(target)
+	}
+
+	fn params(&self) -> &'static [Param] {
+		self.values.params()
+	}
+
+	fn get(&self, name: &str) -> Option<ParamValue> {
+		self.values.get(name)
+	}
+
+	fn set(&mut self, name: &str, value: ParamValue) -> Result<(), ParamError> {
+		self.values.set(name, value)
+	}
+}
+
+/// The classic demoscene plasma, overlapping sine waves mapped to the colour wheel
+pub struct Plasma;
+
+impl Plasma {
+	// indices into `PARAMS`
+	const SPEED: usize = 0;
+	const SCALE: usize = 1;
+
+	pub const PARAMS: &'static [Param; 2] = &[
+		// How fast the waves move
+		Param {
+			name:    "speed",
+			kind:    ParamKind::Float {
+				min: 0.0,
+				max: 10.0,
+			},
+			default: ParamValue::Float(1.0),
+		},
+		// Length of the waves, in pixels
+		Param {
+			name:    "scale",
+			kind:    ParamKind::Float {
+				min: 1.0,
+				max: 64.0,
+			},
+			default: ParamValue::Float(8.0),
+		},
+	];
+
+	pub fn effect() -> ShaderEffect<Self, 2> {
+		ShaderEffect::new(Plasma, Self::PARAMS)
+	}
+}
+
+impl Shader<2> for Plasma {
+	type Colour = HSV;
+
+	fn shade(&self, x: f32, y: f32, t: f32, params: &Values<2>) -> HSV {
+		let speed = params.at(Self::SPEED).as_float().unwrap();
+		let scale = params.at(Self::SCALE).as_float().unwrap();
+		let t = t * speed;
+		let (x, y) = (x / scale, y / scale);
+		let v = sinf(x + t)
+			+ sinf(y + t * 0.7)
+			+ sinf((x + y) * 0.5 + t * 1.3)
+			+ sinf(sqrtf(x * x + y * y) - t);
+		// v is between -4 and 4
+		HSV::new(((v + 4.0) * 32.0) as u8, 255, 255)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	type Frame = Framebuffer<Rgb565, WIDTH, HEIGHT>;
+
+	fn rendered<E: Effect>(effect: &E) -> Frame {
+		let mut frame = Frame::new(Rgb565::BLACK);
+		effect.render(&mut frame).unwrap();
+		frame
+	}
+
+	#[test]
+	fn shades_every_pixel_with_the_time() {
+		let shader = |x: f32, y: f32, t: f32, _: &Values<0>| {
+			Rgb565::new(x as u8 % 32, y as u8, (t * 10.0) as u8)
+		};
+		let mut effect = ShaderEffect::new(shader, &[]);
+		effect.step(Duration::from_millis(500));
+		effect.step(Duration::from_millis(500));
+		assert_eq!(effect.time(), 1.0);
+
+		let frame = rendered(&effect);
+		assert_eq!(frame.pixels()[0][0], Rgb565::new(0, 0, 10));
+		assert_eq!(frame.pixels()[7][5], Rgb565::new(5, 7, 10));
+		assert_eq!(frame.pixels()[31][63], Rgb565::new(31, 31, 10));
+	}
+
+	#[test]
+	fn params_go_through_to_the_values() {
+		let mut plasma = Plasma::effect();
+		assert_eq!(plasma.params().len(), 2);
+		assert_eq!(plasma.get("speed"), Some(ParamValue::Float(1.0)));
+		assert_eq!(plasma.set("scale", ParamValue::Float(16.0)), Ok(()));
+		assert_eq!(plasma.get("scale"), Some(ParamValue::Float(16.0)));
+		assert_eq!(
+			plasma.set("scale", ParamValue::Float(0.5)),
+			Err(ParamError::OutOfRange)
+		);
+		assert_eq!(plasma.get("colour"), None);
+	}
+
+	#[test]
+	fn plasma_moves() {
+		let mut plasma = Plasma::effect();
+		plasma.step(Duration::from_secs(0));
+		let before = rendered(&plasma);
+		plasma.step(Duration::from_millis(250));
+		assert_ne!(rendered(&plasma), before);
+
+		// without speed, time stands still
+		let mut plasma = Plasma::effect();
+		plasma.set("speed", ParamValue::Float(0.0)).unwrap();
+		plasma.step(Duration::from_secs(0));
+		let before = rendered(&plasma);
+		plasma.step(Duration::from_millis(250));
+		assert_eq!(rendered(&plasma), before);
+	}
+
+	#[test]
+	fn plasma_indices_match_the_params() {
+		assert_eq!(Plasma::PARAMS[Plasma::SPEED].name, "speed");
+		assert_eq!(Plasma::PARAMS[Plasma::SCALE].name, "scale");
+	}
+}
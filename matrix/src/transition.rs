@@ -0,0 +1,261 @@
+use crate::{framebuffer::Framebuffer, Effect, HEIGHT, WIDTH};
+use core::time::Duration;
+use embedded_graphics::{drawable::Pixel, pixelcolor::Rgb565, prelude::*, DrawTarget};
+
+/// How the outgoing effect is replaced by the incoming one
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransitionKind {
+	/// Switch instantly
+	Cut,
+	/// Blend every pixel from the old to the new colour
+	Crossfade,
+	/// Reveal the new effect from left to right
+	Wipe,
+	/// Switch pixels over one by one, in a fixed pseudo-random order
+	Dissolve,
+	/// Push the old effect out to the left, with the new one following from the right
+	Slide,
+}
+
+/// Blends between two effects over a fixed duration
+///
+/// Both effects are rendered into their own buffer on `start` and every `step`,
+/// `render` then composites the two buffers into the target.
+pub struct Transition {
+	kind:     TransitionKind,
+	duration: Duration,
+	elapsed:  Duration,
+	from:     Framebuffer<Rgb565, WIDTH, HEIGHT>,
+	to:       Framebuffer<Rgb565, WIDTH, HEIGHT>,
+	/// Whether `from` is a still of an interrupted transition, instead of an effect
+	frozen:   bool,
+}
+
+impl Transition {
+	/// Create a transition, which is idle until `start` is called
+	pub fn new(kind: TransitionKind, duration: Duration) -> Self {
+		Transition {
+			kind,
+			duration,
+			elapsed: duration,
+			from: Framebuffer::new(Rgb565::BLACK),
+			to: Framebuffer::new(Rgb565::BLACK),
+			frozen: false,
+		}
+	}
+
+	pub fn kind(&self) -> TransitionKind {
+		self.kind
+	}
+
+	pub fn set_kind(&mut self, kind: TransitionKind) {
+		self.kind = kind;
+	}
+
+	pub fn duration(&self) -> Duration {
+		self.duration
+	}
+
+	pub fn set_duration(&mut self, duration: Duration) {
+		self.duration = duration;
+	}
+
+	/// Begin transitioning from the start, capturing the current frames of both
+	/// effects so `render` is right straight away
+	///
+	/// If a transition is still running, the new one starts from the frame that
+	/// is on screen right now instead of from `from`.
+	pub fn start<A: Effect, B: Effect>(&mut self, from: &A, to: &B) {
+		if self.is_active() {
+			self.freeze();
+		} else {
+			self.frozen = false;
+			from.render(&mut self.from).unwrap();
+		}
+		to.render(&mut self.to).unwrap();
+		self.elapsed = Duration::from_secs(0);
+	}
+
+	/// Replace `from` by the current composite, which stays as it is from now on
+	fn freeze(&mut self) {
+		let progress = self.progress();
+		// the composite of a pixel only reads pixels at or to the right of it,
+		// so going left to right never reads a pixel that was already replaced
+		for y in 0..HEIGHT {
+			for x in 0..WIDTH {
+				let colour = self.composite(x, y, progress);
+				self.from.pixels_mut()[y][x] = colour;
+			}
+		}
+		self.frozen = true;
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.kind != TransitionKind::Cut && self.elapsed < self.duration
+	}
+
+	/// How far along the transition is, from 0 (only the old effect) to 255 (only the new one)
+	pub fn progress(&self) -> u8 {
+		if !self.is_active() {
+			return 255;
+		}
+		(self.elapsed.as_secs_f32() / self.duration.as_secs_f32() * 255.0) as u8
+	}
+
+	/// Advance the transition and capture the current frames of both effects
+	///
+	/// The effects themselves have to be stepped by the caller.
+	pub fn step<A: Effect, B: Effect>(&mut self, delta: Duration, from: &A, to: &B) {
+		if !self.is_active() {
+			return;
+		}
+		self.elapsed += delta;
+		if !self.frozen {
+			from.render(&mut self.from).unwrap();
+		}
+		to.render(&mut self.to).unwrap();
+	}
+
+	/// Draw the composite of both effects
+	pub fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+		let progress = self.progress();
+		let pixels = (0..HEIGHT).flat_map(move |y| {
+			(0..WIDTH).map(move |x| {
+				Pixel(
+					Point::new(x as i32, y as i32),
+					self.composite(x, y, progress),
+				)
+			})
+		});
+		target.draw_iter(pixels)
+	}
+
+	fn composite(&self, x: usize, y: usize, progress: u8) -> Rgb565 {
+		match self.kind {
+			TransitionKind::Cut => self.to.pixels()[y][x],
+			TransitionKind::Crossfade => {
+				crossfade(self.from.pixels()[y][x], self.to.pixels()[y][x], progress)
+			}
+			TransitionKind::Wipe => {
+				if x < WIDTH * progress as usize / 255 {
+					self.to.pixels()[y][x]
+				} else {
+					self.from.pixels()[y][x]
+				}
+			}
+			TransitionKind::Dissolve => {
+				if dither(x, y) < progress {
+					self.to.pixels()[y][x]
+				} else {
+					self.from.pixels()[y][x]
+				}
+			}
+			TransitionKind::Slide => {
+				let offset = WIDTH * progress as usize / 255;
+				if x + offset < WIDTH {
+					self.from.pixels()[y][x + offset]
+				} else {
+					self.to.pixels()[y][x + offset - WIDTH]
+				}
+			}
+		}
+	}
+}
+
+fn lerp(a: u8, b: u8, amount: u8) -> u8 {
+	let (a, b, amount) = (a as i16, b as i16, amount as i16);
+	(a + (b - a) * amount / 255) as u8
+}
+
+fn crossfade(from: Rgb565, to: Rgb565, amount: u8) -> Rgb565 {
+	Rgb565::new(
+		lerp(from.r(), to.r(), amount),
+		lerp(from.g(), to.g(), amount),
+		lerp(from.b(), to.b(), amount),
+	)
+}
+
+/// A fixed, evenly spread threshold for every pixel
+fn dither(x: usize, y: usize) -> u8 {
+	let mut h = (x as u32).wrapping_mul(0x9e37_79b1) ^ (y as u32).wrapping_mul(0x85eb_ca77);
+	h ^= h >> 15;
+	h = h.wrapping_mul(0x2c1b_3c6d);
+	h ^= h >> 12;
+	(h >> 24) as u8
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Fills the screen with one colour
+	struct Solid(Rgb565);
+
+	impl Effect for Solid {
+		fn step(&mut self, _delta: Duration) {}
+
+		fn render<D: DrawTarget<Rgb565>>(&self, target: &mut D) -> Result<(), D::Error> {
+			target.clear(self.0)
+		}
+	}
+
+	fn rendered(transition: &Transition) -> Framebuffer<Rgb565, WIDTH, HEIGHT> {
+		let mut frame = Framebuffer::new(Rgb565::BLACK);
+		transition.render(&mut frame).unwrap();
+		frame
+	}
+
+	#[test]
+	fn progress() {
+		let (a, b) = (Solid(Rgb565::RED), Solid(Rgb565::BLUE));
+		let mut transition = Transition::new(TransitionKind::Crossfade, Duration::from_secs(2));
+		assert!(!transition.is_active());
+		assert_eq!(transition.progress(), 255);
+
+		transition.start(&a, &b);
+		assert!(transition.is_active());
+		assert_eq!(transition.progress(), 0);
+
+		transition.step(Duration::from_secs(1), &a, &b);
+		assert_eq!(transition.progress(), 127);
+
+		transition.step(Duration::from_secs(1), &a, &b);
+		assert!(!transition.is_active());
+		assert_eq!(transition.progress(), 255);
+	}
+
+	#[test]
+	fn cut_is_never_active() {
+		let (a, b) = (Solid(Rgb565::RED), Solid(Rgb565::BLUE));
+		let mut transition = Transition::new(TransitionKind::Cut, Duration::from_secs(2));
+		transition.start(&a, &b);
+		assert!(!transition.is_active());
+	}
+
+	#[test]
+	fn renders_right_after_start() {
+		let (a, b) = (Solid(Rgb565::RED), Solid(Rgb565::BLUE));
+		let mut transition = Transition::new(TransitionKind::Wipe, Duration::from_secs(1));
+		transition.start(&a, &b);
+		assert_eq!(rendered(&transition).pixels()[0][0], Rgb565::RED);
+	}
+
+	#[test]
+	fn restart_continues_from_the_screen() {
+		let (a, b, c) = (
+			Solid(Rgb565::RED),
+			Solid(Rgb565::BLUE),
+			Solid(Rgb565::GREEN),
+		);
+		let mut transition = Transition::new(TransitionKind::Wipe, Duration::from_secs(2));
+		transition.start(&a, &b);
+		transition.step(Duration::from_secs(1), &a, &b);
+		let before = rendered(&transition);
+
+		// c replaces whatever was on screen, not a
+		transition.start(&b, &c);
+		assert_eq!(rendered(&transition), before);
+		transition.step(Duration::from_millis(1), &b, &c);
+		assert_eq!(rendered(&transition).pixels()[0][WIDTH - 1], Rgb565::RED);
+	}
+}
@@ -1,25 +1,18 @@
 #![no_main]
 #![no_std]
-#![feature(alloc_error_handler)]
 
-extern crate alloc;
-
-use alloc::boxed::Box;
-use core::{
-	alloc::{GlobalAlloc, Layout},
-	borrow::BorrowMut,
-	convert::TryInto,
-};
-use cortex_m::{
-	asm::delay,
-	interrupt::{free, Mutex},
-	peripheral::DWT,
-};
+use core::{convert::TryInto, time::Duration};
+use cortex_m::{asm::delay, peripheral::DWT};
 use cortex_m_rt::{exception, ExceptionFrame};
-use cortex_m_semihosting::{debug, hprintln};
+use cortex_m_semihosting::hprintln;
 use embedded_hal::blocking::delay::DelayUs;
-use heapless::pool;
-use matrix::{effect_sched, hub75::Hub75, CloudEffect, Effect, RectEffect};
+use matrix::{
+	any::AnyEffect,
+	hub75::Scan,
+	runner::{Board, Continuous, Display, Frame, FrameStats, Parts, Runner, Split, Stepper, Ticks},
+	CloudEffect,
+	Effect,
+};
 use panic_semihosting as _;
 use rtic::{
 	app,
@@ -34,17 +27,15 @@ use stm32l4xx_hal::{
 		PushPull,
 	},
 	prelude::*,
-	rcc::Rcc,
-	stm32l4::stm32l4x6::Interrupt,
 	time::Hertz,
 };
 
 type Outputs = (
 	PA9<Output<PushPull>>, // R1
-	PB6<Output<PushPull>>, // R2
-	PA6<Output<PushPull>>, // G1
-	PC7<Output<PushPull>>, // G2
-	PA5<Output<PushPull>>, // B1
+	PB6<Output<PushPull>>, // G1
+	PA6<Output<PushPull>>, // B1
+	PC7<Output<PushPull>>, // R2
+	PA5<Output<PushPull>>, // G2
 	PA7<Output<PushPull>>, // B2
 	//
 	PB11<Output<PushPull>>, // A
@@ -53,12 +44,20 @@ type Outputs = (
 	PA12<Output<PushPull>>, // D
 	//
 	PC9<Output<PushPull>>, // CLK
-	PB8<Output<PushPull>>, // OE
-	PB9<Output<PushPull>>, // LAT
+	PB8<Output<PushPull>>, // LAT
+	PB9<Output<PushPull>>, // OE
 );
 
+// in cycles of the 80 MHz sysclk
 const REFRESH_PERIOD: u32 = 320_000;
 const STEP_PERIOD: u32 = 1_333_333;
+const FPS: u32 = 80_000_000 / STEP_PERIOD;
+// Seconds between two reports of the frame times
+const REPORT_INTERVAL: u32 = 10;
+
+fn cycles_to_duration(cycles: u32) -> Duration {
+	Duration::from_micros(cycles as u64 / 80)
+}
 
 pub struct Delay {
 	freq: Hertz,
@@ -79,50 +78,38 @@ impl DelayUs<u8> for Delay {
 impl DelayUs<u32> for Delay {
 	fn delay_us(&mut self, us: u32) {
 		let cycles = us * (self.freq.0 / 1_000_000);
-		delay(1);
+		delay(cycles);
 	}
 }
 
-pool!(P: [u8; 4096]);
+struct Stm32 {
+	dp: stm32l4xx_hal::stm32::Peripherals,
+}
 
-#[global_allocator]
-static ALLOC: P = P {};
+/// The cycle counter of the DWT, the same one RTIC schedules with
+struct CycleCounter {
+	frequency: u32,
+}
 
-unsafe impl GlobalAlloc for P {
-	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-		P::alloc(self, layout)
+impl Ticks for CycleCounter {
+	fn now(&mut self) -> u32 {
+		DWT::get_cycle_count()
 	}
 
-	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-		P::dealloc(self, ptr, layout);
+	fn frequency(&self) -> u32 {
+		self.frequency
 	}
 }
 
-#[alloc_error_handler]
-fn foo(ly: core::alloc::Layout) -> ! {
-	panic!("failed to allocate {:?}", ly);
-}
+impl Board for Stm32 {
+	type Outputs = Outputs;
+	type Delay = Delay;
+	// refreshing is scheduled by RTIC
+	type Timer = Continuous;
+	type Ticks = CycleCounter;
 
-#[app(device = stm32l4xx_hal::stm32, peripherals = true, monotonic = rtic::cyccnt::CYCCNT)]
-const APP: () = {
-	struct Resources {
-		matrix: Hub75<Outputs>,
-		delay:  Delay,
-		step:   CloudEffect,
-	}
-
-	#[init(schedule = [refresh_matrix, refresh_effect, write_effect])]
-	fn init(mut cx: init::Context) -> init::LateResources {
-		// Initialize (enable) the monotonic timer (CYCCNT)
-		cx.core.DCB.enable_trace();
-		// required on Cortex-M7 devices that software lock the DWT (e.g. STM32F7)
-		DWT::unlock();
-		cx.core.DWT.enable_cycle_counter();
-
-		// Cortex-M peripherals
-		let cp: rtic::Peripherals = cx.core;
-		// Device specific peripherals
-		let dp: stm32l4xx_hal::stm32::Peripherals = cx.device;
+	fn split(self) -> Parts<Self> {
+		let dp = self.dp;
 
 		let mut flash = dp.FLASH.constrain();
 		let mut rcc = dp.RCC.constrain();
@@ -138,14 +125,43 @@ const APP: () = {
 		let gpiob = dp.GPIOB.split(&mut rcc.ahb2);
 		let gpioc = dp.GPIOC.split(&mut rcc.ahb2);
 
-		let pins = init_pins(gpioa, gpiob, gpioc);
+		Parts {
+			outputs: init_pins(gpioa, gpiob, gpioc),
+			delay:   Delay::new(clocks.sysclk()),
+			timer:   Continuous,
+			ticks:   CycleCounter {
+				frequency: clocks.sysclk().0,
+			},
+		}
+	}
+}
+
+#[app(device = stm32l4xx_hal::stm32, peripherals = true, monotonic = rtic::cyccnt::CYCCNT)]
+const APP: () = {
+	struct Resources {
+		display: Display<Outputs, Delay>,
+		stepper: Stepper<AnyEffect, CycleCounter>,
+		back:    Frame,
+		scan:    Scan,
+		stats:   FrameStats,
+	}
 
-		let mut matrix = Hub75::new(pins, 3);
-		let delay = Delay::new(clocks.sysclk());
-		let mut step = CloudEffect::new();
-		step.step();
-		step.write(&mut matrix);
-		// let mut step = effect_sched();
+	#[init(schedule = [refresh_matrix, step_effect])]
+	fn init(mut cx: init::Context) -> init::LateResources {
+		// Initialize (enable) the monotonic timer (CYCCNT)
+		cx.core.DCB.enable_trace();
+		// required on Cortex-M7 devices that software lock the DWT (e.g. STM32F7)
+		DWT::unlock();
+		cx.core.DWT.enable_cycle_counter();
+
+		let board = Stm32 { dp: cx.device };
+		let effect = AnyEffect::from(CloudEffect::new());
+		let Split {
+			display,
+			stepper,
+			back,
+			..
+		} = Runner::new(board, effect, FPS).split();
 
 		// semantically, the monotonic timer is frozen at time "zero" during `init`
 		// NOTE do *not* call `Instant::now` in this context; it will return a nonsense value
@@ -156,96 +172,79 @@ const APP: () = {
 		cx.schedule
 			.refresh_matrix(now + REFRESH_PERIOD.cycles())
 			.unwrap();
-		// cx.schedule
-		// 	.refresh_effect(now + STEP_PERIOD.cycles())
-		// 	.unwrap();
-		cx.schedule
-			.write_effect(now + STEP_PERIOD.cycles())
-			.unwrap();
-		// rtic::pend(Interrupt::UART4);
-
-		// let step = Mutex::new(Box::new(step));
-		// let matrix = matrix;
+		cx.schedule.step_effect(now + STEP_PERIOD.cycles()).unwrap();
 
 		init::LateResources {
-			matrix,
-			delay,
-			step,
+			display,
+			stepper,
+			back,
+			scan: Scan::new(),
+			stats: FrameStats::new(cycles_to_duration(STEP_PERIOD)),
 		}
 	}
 
-	// #[task(schedule = [refresh_matrix])]
-	// fn refresh_matrix(cx: refresh_matrix::Context) {
-	// 	hprintln!("refresh_matrix @ {:?}", Instant::now()).unwrap();
-	// 	cx.schedule
-	// 		.refresh_matrix(cx.scheduled + PERIOD.cycles())
-	// 		.unwrap();
-	// }
-
-	#[task(priority = 255, resources = [matrix, delay], schedule = [refresh_matrix])]
+	/// Scans out the front buffer, preempting the effect so the panel doesn't flicker
+	#[task(priority = 2, resources = [display], schedule = [refresh_matrix])]
 	fn refresh_matrix(cx: refresh_matrix::Context) {
-		// hprintln!("refresh_matrix @ {:?}", Instant::now()).unwrap();
-		// let matrix_mutex: &mut Mutex<Hub75<_>> = cx.resources.matrix;
-		// free(|cs| {
-		// 	let mut matrix = matrix_mutex.borrow(cs);
-		// 	matrix.output(&mut (*cx.resources.delay));
-		// });
-		cx.resources.matrix.output(&mut (*cx.resources.delay));
+		cx.resources.display.refresh();
 		cx.schedule
 			.refresh_matrix(cx.scheduled + REFRESH_PERIOD.cycles())
 			.unwrap();
 	}
 
-	#[task(resources = [step], schedule = [write_effect])]
-	fn refresh_effect(mut cx: refresh_effect::Context) {
-		// hprintln!("refresh_effect @ {:?}", Instant::now()).unwrap();
-		let step = &mut *cx.resources.step;
+	/// Steps the effect into the back buffer, and swaps it to the front
+	#[task(priority = 1, resources = [display, stepper, back, scan, stats], schedule = [step_effect])]
+	fn step_effect(mut cx: step_effect::Context) {
 		let before = Instant::now();
-		step.step();
-		let after = Instant::now();
-		let duration: u32 = (after - before).try_into().unwrap();
-		// hprintln!(
-		// 	"refresh_effect @ {:?}, took: {:?}",
-		// 	Instant::now(),
-		// 	duration
-		// )
-		// .unwrap();
-		cx.schedule
-			.write_effect(cx.scheduled + STEP_PERIOD.cycles())
-			.unwrap();
-	}
 
-	#[task(resources = [matrix, step], schedule = [refresh_effect, write_effect])]
-	fn write_effect(mut cx: write_effect::Context) {
-		// hprintln!("refresh_effect @ {:?}", Instant::now()).unwrap();
-		let step = &mut *cx.resources.step;
-		let before = Instant::now();
-		cx.resources.matrix.lock(|mut matrix| {
-			step.write(&mut matrix);
-		});
-		let after = Instant::now();
-		let duration: u32 = (after - before).try_into().unwrap();
-		// hprintln!("write_effect @ {:?}, took: {:?}", Instant::now(), duration).unwrap();
+		let stepper = cx.resources.stepper;
+		let back = cx.resources.back;
+		let scan = cx.resources.scan;
+		stepper.step();
+		stepper.effect.render(back).unwrap();
+		scan.blit(back);
+		// the refresh can't run while the display is locked, so the new frame
+		// always shows up between two scans, never halfway through one. The
+		// frame is converted already, so a refresh waits for a copy at most.
+		cx.resources
+			.display
+			.lock(|display| display.matrix.swap(scan));
+
+		let took: u32 = (Instant::now() - before).try_into().unwrap();
+		cx.resources.stats.record(cycles_to_duration(took));
+
 		cx.schedule
-			.write_effect(cx.scheduled + STEP_PERIOD.cycles())
+			.step_effect(cx.scheduled + STEP_PERIOD.cycles())
 			.unwrap();
 	}
 
-	#[idle]
-	fn idle(cx: idle::Context) -> ! {
-		rtic::pend(Interrupt::UART4);
-		rtic::pend(Interrupt::UART5);
-		// extern "C" {
-		// 	fn UART4();
-		// }
-		//
-		// unsafe { UART4() }
-
-		// cx.schedule
-		// 	.refresh_matrix(Instant::now() + PERIOD.cycles())
-		// 	.unwrap();
-
-		loop {}
+	/// Reports the frame times, outside of the tasks that are timed
+	///
+	/// Semihosting halts the core while printing, so the panel still stalls
+	/// for a moment with every report.
+	#[idle(resources = [stats])]
+	fn idle(mut cx: idle::Context) -> ! {
+		loop {
+			let report = cx.resources.stats.lock(|stats| {
+				if stats.frames < FPS * REPORT_INTERVAL {
+					return None;
+				}
+				let report = *stats;
+				stats.reset();
+				Some(report)
+			});
+			if let Some(stats) = report {
+				hprintln!(
+					"{} frames, {}/{}/{} us min/avg/max, {} overruns",
+					stats.frames,
+					stats.min_us,
+					stats.average_us(),
+					stats.max_us,
+					stats.overruns
+				)
+				.unwrap();
+			}
+		}
 	}
 
 	extern "C" {
@@ -21,28 +21,29 @@ use crate::hal::{
 	},
 	interrupt,
 	prelude::*,
-	stm32::Interrupt,
-};
-use core::{
-	borrow::BorrowMut,
-	cell::{Cell, RefCell, UnsafeCell},
-	ops::DerefMut,
+	stm32::{Interrupt, TIM7},
+	timer::{Event, Timer},
 };
+use core::cell::RefCell;
 use cortex_m::{
 	interrupt::{free, Mutex},
-	peripheral::NVIC,
+	peripheral::{DWT, NVIC},
 };
 use cortex_m_semihosting::hprintln;
-use hal::timer::{Event, Timer};
-use matrix::{hub75::Hub75, Effect as _, RectEffect};
+use matrix::{
+	hub75::Scan,
+	new_effect,
+	runner::{Board, Display, Parts, Runner, Split, Ticks},
+	Effect as _,
+};
 use rt::{entry, exception, ExceptionFrame};
 
 type Outputs = (
 	PA9<Output<PushPull>>, // R1
-	PB6<Output<PushPull>>, // R2
-	PA6<Output<PushPull>>, // G1
-	PC7<Output<PushPull>>, // G2
-	PA5<Output<PushPull>>, // B1
+	PB6<Output<PushPull>>, // G1
+	PA6<Output<PushPull>>, // B1
+	PC7<Output<PushPull>>, // R2
+	PA5<Output<PushPull>>, // G2
 	PA7<Output<PushPull>>, // B2
 	//
 	PB11<Output<PushPull>>, // A
@@ -51,43 +52,132 @@ type Outputs = (
 	PA12<Output<PushPull>>, // D
 	//
 	PC9<Output<PushPull>>, // CLK
-	PB8<Output<PushPull>>, // OE
-	PB9<Output<PushPull>>, // LAT
+	PB8<Output<PushPull>>, // LAT
+	PB9<Output<PushPull>>, // OE
 );
 
-static mut MATRIX: Mutex<RefCell<Option<(Hub75<Outputs>, Delay)>>> = Mutex::new(RefCell::new(None));
+// How often the panel is scanned out
+const REFRESH_RATE: u32 = 200;
+// How often the effect is stepped
+const FPS: u32 = 60;
+
+// The panel, shared with the TIM7 interrupt that refreshes it
+static DISPLAY: Mutex<RefCell<Option<Display<Outputs, Delay>>>> = Mutex::new(RefCell::new(None));
+
+struct Stm32 {
+	cp: cortex_m::Peripherals,
+	dp: hal::stm32::Peripherals,
+}
+
+/// The cycle counter of the DWT, running at the system clock
+struct CycleCounter {
+	frequency: u32,
+}
 
-fn use_matrix<T: Fn(&mut Hub75<Outputs>, &mut Delay)>(func: T) {
-	free(|cs| unsafe {
-		let mut data_ref = MATRIX.borrow(cs).borrow_mut();
-		if let Some(ref mut data) = data_ref.deref_mut() {
-			func(&mut data.0, &mut data.1)
+impl Ticks for CycleCounter {
+	fn now(&mut self) -> u32 {
+		DWT::get_cycle_count()
+	}
+
+	fn frequency(&self) -> u32 {
+		self.frequency
+	}
+}
+
+impl Board for Stm32 {
+	type Outputs = Outputs;
+	type Delay = Delay;
+	type Timer = Timer<TIM7>;
+	type Ticks = CycleCounter;
+
+	const BRIGHTNESS_BITS: u8 = 1;
+
+	fn split(self) -> Parts<Self> {
+		let Stm32 { mut cp, dp } = self;
+
+		let mut flash = dp.FLASH.constrain();
+		let mut rcc = dp.RCC.constrain();
+		let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+		// TRY the other clock configuration
+		// let clocks = rcc.cfgr.freeze(&mut flash.acr);
+		let clocks = rcc
+			.cfgr
+			.sysclk(80.mhz())
+			.pclk1(80.mhz())
+			.pclk2(80.mhz())
+			.freeze(&mut flash.acr, &mut pwr);
+
+		let gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+		let gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+		let gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+
+		cp.DCB.enable_trace();
+		cp.DWT.enable_cycle_counter();
+
+		Parts {
+			outputs: init_pins(gpioa, gpiob, gpioc),
+			delay:   Delay::new(cp.SYST, clocks),
+			timer:   Timer::tim7(dp.TIM7, REFRESH_RATE.hz(), clocks, &mut rcc.apb1r1),
+			ticks:   CycleCounter {
+				frequency: clocks.sysclk().0,
+			},
 		}
-	});
+	}
 }
 
 #[entry]
 fn main() -> ! {
-	let cp = cortex_m::Peripherals::take().unwrap();
-	let dp = hal::stm32::Peripherals::take().unwrap();
-
-	let mut flash = dp.FLASH.constrain();
-	let mut rcc = dp.RCC.constrain();
-	let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
-
-	// TRY the other clock configuration
-	// let clocks = rcc.cfgr.freeze(&mut flash.acr);
-	let clocks = rcc
-		.cfgr
-		.sysclk(80.mhz())
-		.pclk1(80.mhz())
-		.pclk2(80.mhz())
-		.freeze(&mut flash.acr, &mut pwr);
-
-	let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
-	let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
-	let mut gpioc = dp.GPIOC.split(&mut rcc.ahb2);
+	let board = Stm32 {
+		cp: cortex_m::Peripherals::take().unwrap(),
+		dp: hal::stm32::Peripherals::take().unwrap(),
+	};
+
+	hprintln!("start").unwrap();
+
+	let Split {
+		display,
+		mut stepper,
+		mut timer,
+		mut back,
+	} = Runner::new(board, new_effect(), FPS).split();
+	let mut scan = Scan::new();
+
+	free(|cs| DISPLAY.borrow(cs).replace(Some(display)));
+	timer.listen(Event::TimeOut);
+	unsafe {
+		NVIC::unmask(Interrupt::TIM7);
+	}
+
+	loop {
+		if !stepper.due() {
+			continue;
+		}
+		stepper.step();
+		stepper.effect.render(&mut back).unwrap();
+		// convert the frame before taking the panel, so the refresh only ever
+		// waits for a copy
+		scan.blit(&back);
+		free(|cs| {
+			if let Some(display) = DISPLAY.borrow(cs).borrow_mut().as_mut() {
+				display.matrix.swap(&mut scan);
+			}
+		});
+	}
+}
+
+#[interrupt]
+fn TIM7() {
+	free(|cs| {
+		if let Some(display) = DISPLAY.borrow(cs).borrow_mut().as_mut() {
+			display.refresh();
+		}
+	});
+	let dp = unsafe { hal::stm32::Peripherals::steal() };
+	dp.TIM7.sr.write(|w| w.uif().clear_bit());
+}
 
+fn init_pins(mut gpioa: PartsA, mut gpiob: PartsB, mut gpioc: PartsC) -> Outputs {
 	let r1 = gpioa
 		.pa9
 		.into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
@@ -130,46 +220,7 @@ fn main() -> ! {
 		.pb8
 		.into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
 
-	unsafe {
-		NVIC::unmask(Interrupt::TIM7);
-	}
-
-	let mut timer = Timer::tim7(dp.TIM7, 1.hz(), clocks, &mut rcc.apb1r1);
-
-	let mut matrix: Hub75<Outputs> =
-		Hub75::new((r1, g1, b1, r2, g2, b2, a, b, c, d, clk, lat, oe), 1);
-	let mut delay = Delay::new(cp.SYST, clocks);
-
-	free(|cs| unsafe {
-		MATRIX.borrow(cs).replace(Some((matrix, delay)));
-	});
-
-	timer.clear_interrupt(Event::TimeOut);
-	hprintln!("start");
-
-	let mut effect = RectEffect::new();
-	effect.step();
-	use_matrix(|matrix, _| effect.write(matrix));
-
-	hprintln!("effect");
-
-	timer.listen(Event::TimeOut);
-	hprintln!("bonk");
-	loop {
-		effect.step();
-		use_matrix(|matrix, _| effect.write(matrix));
-		hprintln!("loop");
-	}
-}
-
-#[interrupt]
-fn TIM7() {
-	use_matrix(|matrix, delay| {
-		matrix.output(delay);
-	});
-	let dp = unsafe { hal::stm32::Peripherals::steal() };
-	dp.TIM7.sr.write(|w| w.uif().clear_bit());
-	// hprintln!("timer");
+	(r1, g1, b1, r2, g2, b2, a, b, c, d, clk, lat, oe)
 }
 
 #[exception]